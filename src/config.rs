@@ -0,0 +1,398 @@
+use crate::duration::TrackedTime;
+use chrono::{
+    format::{Item, StrftimeItems},
+    NaiveDate,
+};
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const APP_DIR: &str = "rusty_timer_cli";
+pub const DATA_FILE_NAME: &str = "time_tracker_data.json";
+
+/// Environment variable that overrides the data file location from the config file.
+pub const DATA_FILE_ENV: &str = "RUSTY_TIMER_DATA_FILE";
+
+/// Every key `config get` and `config set` accept.
+pub const KEYS: &[&str] = &[
+    "date_format",
+    "default_project",
+    "data_file",
+    "currency",
+    "keys.stop",
+    "keys.switch",
+    "keys.pause",
+    "keys.resume",
+    "keys.note",
+    "keys.keep",
+    "keys.discard",
+    "keys.give",
+    "goals.daily",
+    "goals.weekly",
+    "idle.after",
+    "idle.command",
+    "idle.file",
+    "colors.success",
+    "colors.info",
+    "colors.warning",
+];
+
+/// User settings read from `config.toml` in the platform config directory
+/// (`$XDG_CONFIG_HOME/rusty_timer_cli/` on Linux).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// `chrono` format used to display days and to parse the days users type.
+    pub date_format: String,
+    /// Project started by `start` when no name is given.
+    pub default_project: Option<String>,
+    pub data_file: Option<PathBuf>,
+    /// Currency of the rates of projects and clients that don't name their own.
+    pub currency: String,
+    pub keys: Keys,
+    pub colors: Colors,
+    pub goals: Goals,
+    pub idle: Idle,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            date_format: String::from("%m/%d/%y"),
+            default_project: None,
+            data_file: None,
+            currency: String::from("USD"),
+            keys: Keys::default(),
+            colors: Colors::default(),
+            goals: Goals::default(),
+            idle: Idle::default(),
+        }
+    }
+}
+
+/// Keys understood by the `start` loop.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Keys {
+    pub stop: char,
+    pub switch: char,
+    pub pause: char,
+    pub resume: char,
+    pub note: char,
+    /// Answers to the question about the time away after the user was idle.
+    pub keep: char,
+    pub discard: char,
+    pub give: char,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys {
+            stop: 'a',
+            switch: 's',
+            pause: 'p',
+            resume: 'r',
+            note: 'n',
+            keep: 'k',
+            discard: 'd',
+            give: 'g',
+        }
+    }
+}
+
+impl Keys {
+    /// Each key with what it does, for the footer of the `start` dashboard.
+    pub fn help(&self) -> [(char, &'static str); 5] {
+        [
+            (self.stop, "stop and quit"),
+            (self.switch, "switch project"),
+            (self.pause, "pause"),
+            (self.resume, "resume"),
+            (self.note, "note"),
+        ]
+    }
+
+    /// Each key with its name in the config file, with the answers to the idle question only
+    /// when `idle` asks it.
+    fn named(&self, idle: bool) -> Vec<(&'static str, char)> {
+        let mut keys = vec![
+            ("stop", self.stop),
+            ("switch", self.switch),
+            ("pause", self.pause),
+            ("resume", self.resume),
+            ("note", self.note),
+        ];
+        if idle {
+            keys.extend([
+                ("keep", self.keep),
+                ("discard", self.discard),
+                ("give", self.give),
+            ]);
+        }
+        keys
+    }
+}
+
+/// Time to track each day and each week, shown by `goals` and in the `start` loop. Written
+/// the way durations are typed elsewhere, e.g. "6h" or "7h30m".
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Goals {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weekly: Option<String>,
+}
+
+impl Goals {
+    pub fn daily(&self) -> Option<TrackedTime> {
+        self.daily.as_deref().and_then(TrackedTime::parse_human)
+    }
+
+    pub fn weekly(&self) -> Option<TrackedTime> {
+        self.weekly.as_deref().and_then(TrackedTime::parse_human)
+    }
+}
+
+/// When the `start` loop considers the user away and pauses the session: after `after` (e.g.
+/// "10m") without a key press in the tracker or activity reported by the idle sources. Off
+/// while `after` isn't set.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Idle {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    /// Command printing how long the user has been idle in milliseconds, like `xprintidle`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// File that something else touches whenever the user is active.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+}
+
+impl Idle {
+    pub fn after(&self) -> Option<TrackedTime> {
+        self.after.as_deref().and_then(TrackedTime::parse_human)
+    }
+}
+
+/// Colors of the messages printed by the tracker, by kind of message. Any name understood by
+/// crossterm works, e.g. "dark_green" or "grey".
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Colors {
+    pub success: String,
+    pub info: String,
+    pub warning: String,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            success: String::from("green"),
+            info: String::from("blue"),
+            warning: String::from("red"),
+        }
+    }
+}
+
+impl Colors {
+    pub fn success(&self) -> Color {
+        Color::try_from(self.success.as_str()).unwrap_or(Color::Green)
+    }
+
+    pub fn info(&self) -> Color {
+        Color::try_from(self.info.as_str()).unwrap_or(Color::Blue)
+    }
+
+    pub fn warning(&self) -> Color {
+        Color::try_from(self.warning.as_str()).unwrap_or(Color::Red)
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(APP_DIR).join("config.toml"))
+    }
+
+    /// Loads the config file, falling back to the defaults if there is none.
+    pub fn load() -> io::Result<Self> {
+        let Some(path) = Config::path() else {
+            return Ok(Config::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => Config::parse(&contents).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e),
+                )
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn parse(contents: &str) -> Result<Self, String> {
+        let config: Config = toml::from_str(contents).map_err(|e| e.to_string())?;
+        for color in [
+            &config.colors.success,
+            &config.colors.info,
+            &config.colors.warning,
+        ] {
+            if Color::try_from(color.as_str()).is_err() {
+                return Err(format!("unknown color {:?}", color));
+            }
+        }
+        // The answers to the idle question are taken while the other keys work too.
+        let keys = config.keys.named(config.idle.after.is_some());
+        for (i, (name, key)) in keys.iter().enumerate() {
+            if let Some((other, _)) = keys[..i].iter().find(|(_, other)| other == key) {
+                return Err(format!(
+                    "keys.{} and keys.{} are both {:?}",
+                    other, name, key
+                ));
+            }
+        }
+        for goal in [&config.goals.daily, &config.goals.weekly]
+            .into_iter()
+            .flatten()
+        {
+            if TrackedTime::parse_human(goal).is_none() {
+                return Err(format!("goal {:?} is not a duration like \"6h\"", goal));
+            }
+        }
+        if let Some(ref after) = config.idle.after {
+            if TrackedTime::parse_human(after).is_none_or(|after| after <= TrackedTime::ZERO) {
+                return Err(format!(
+                    "idle time {:?} is not a duration like \"10m\"",
+                    after
+                ));
+            }
+        }
+        if StrftimeItems::new(&config.date_format).any(|item| item == Item::Error) {
+            return Err(format!(
+                "date format {:?} isn't a valid strftime format",
+                config.date_format
+            ));
+        }
+        let sample = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        // Formatting fails instead of panicking when the format asks for more than a day has.
+        let mut formatted = String::new();
+        if write!(formatted, "{}", sample.format(&config.date_format)).is_err()
+            || NaiveDate::parse_from_str(&formatted, &config.date_format) != Ok(sample)
+        {
+            return Err(format!(
+                "date format {:?} can't be read back as a day",
+                config.date_format
+            ));
+        }
+        Ok(config)
+    }
+
+    pub fn show(&self) -> String {
+        toml::to_string(self).expect("Failed to serialize config")
+    }
+
+    /// Looks up a dotted key such as `keys.pause`.
+    pub fn get(&self, key: &str) -> Option<String> {
+        let value = toml::Value::try_from(self).expect("Failed to serialize config");
+        let value = key
+            .split('.')
+            .try_fold(&value, |value, part| value.get(part))?;
+        Some(match value {
+            toml::Value::String(s) => s.clone(),
+            value => value.to_string(),
+        })
+    }
+
+    /// Sets a dotted key in the config file, keeping whatever else the file contains. An empty
+    /// value removes the key, restoring its default.
+    pub fn set(&mut self, key: &str, value: &str) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        if !KEYS.contains(&key) {
+            return Err(invalid(format!("unknown config key {:?}", key)));
+        }
+        let path = Config::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        let mut table = match fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .parse::<toml::Table>()
+                .map_err(|e| invalid(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => toml::Table::new(),
+            Err(e) => return Err(e),
+        };
+
+        let (parents, last) = match key.rsplit_once('.') {
+            Some((parent, last)) => (Some(parent), last),
+            None => (None, key),
+        };
+        let section = match parents {
+            Some(parent) => table
+                .entry(parent)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| invalid(format!("{:?} is not a table", parent)))?,
+            None => &mut table,
+        };
+        if value.is_empty() {
+            section.remove(last);
+        } else {
+            section.insert(last.to_string(), toml::Value::String(value.to_string()));
+        }
+
+        let contents = table.to_string();
+        *self = Config::parse(&contents).map_err(invalid)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)
+    }
+
+    /// Resolves where the data file lives: the `--data-file` flag wins over the environment
+    /// variable, which wins over the config file, which wins over the platform data directory
+    /// (`$XDG_DATA_HOME/rusty_timer_cli/` on Linux).
+    pub fn data_file(&self, flag: Option<&Path>) -> PathBuf {
+        if let Some(path) = flag {
+            return path.to_path_buf();
+        }
+        if let Some(path) = env::var_os(DATA_FILE_ENV).filter(|path| !path.is_empty()) {
+            return PathBuf::from(path);
+        }
+        if let Some(ref path) = self.data_file {
+            return path.clone();
+        }
+        dirs::data_dir()
+            .map(|dir| dir.join(APP_DIR).join(DATA_FILE_NAME))
+            .unwrap_or_else(|| PathBuf::from(DATA_FILE_NAME))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_date_formats() {
+        assert!(Config::parse("date_format = \"%d.%m.%Y\"").is_ok());
+        assert!(Config::parse("date_format = \"%Q\"").is_err());
+        assert!(Config::parse("date_format = \"%Y %Q\"").is_err());
+        assert!(Config::parse("date_format = \"%H:%M\"").is_err());
+    }
+
+    #[test]
+    fn rejects_keys_used_twice() {
+        assert!(Config::parse("").is_ok());
+        assert!(Config::parse("[keys]\nstop = \"x\"\nkeep = \"a\"").is_ok());
+        assert!(Config::parse("[keys]\nstop = \"d\"").is_ok());
+        assert_eq!(
+            Config::parse("[keys]\nstop = \"d\"\n[idle]\nafter = \"10m\"").unwrap_err(),
+            "keys.stop and keys.discard are both 'd'"
+        );
+        assert!(Config::parse("[keys]\ngive = \"s\"\n[idle]\nafter = \"10m\"").is_err());
+    }
+}
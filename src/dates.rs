@@ -0,0 +1,116 @@
+use chrono::{Datelike, Months, NaiveDate, TimeDelta, Weekday};
+
+/// Reads a day typed by the user relative to `today`. Besides dates in the configured
+/// `date_format` and ISO 8601 ("2024-02-07"), this understands "today", "yesterday", weekday
+/// names ("monday" or "mon", meaning the most recent one, today included) and offsets such as
+/// "-3d" or "-2w".
+pub fn parse_day(input: &str, date_format: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim();
+    let lowercase = input.to_lowercase();
+    match lowercase.as_str() {
+        "today" => return Some(today),
+        "yesterday" => return today.pred_opt(),
+        _ => {}
+    }
+    if let Ok(weekday) = lowercase.parse::<Weekday>() {
+        let days_back =
+            (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+        return today.checked_sub_signed(TimeDelta::days(days_back.into()));
+    }
+    if let Some(offset) = lowercase.strip_prefix('-') {
+        let days = match offset.strip_suffix(['d', 'w']) {
+            Some(amount) if offset.ends_with('w') => amount
+                .parse::<i64>()
+                .ok()
+                .and_then(|weeks| weeks.checked_mul(7)),
+            Some(amount) => amount.parse::<i64>().ok(),
+            None => None,
+        };
+        if let Some(days) = days {
+            return today.checked_sub_signed(TimeDelta::try_days(days)?);
+        }
+    }
+    NaiveDate::parse_from_str(input, date_format)
+        .or_else(|_| NaiveDate::parse_from_str(input, "%Y-%m-%d"))
+        .ok()
+}
+
+/// `parse_day` for days given on the command line, failing with a message that says what was
+/// expected.
+pub fn day_arg(input: &str, date_format: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    parse_day(input, date_format, today).ok_or_else(|| {
+        format!(
+            "\u{26a0} Day {:?} doesn't match the date format {:?}, an ISO 8601 date or a relative day like \"yesterday\"!",
+            input, date_format
+        )
+    })
+}
+
+/// Monday and Sunday of the week `date` falls in.
+pub fn week_of(date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let monday = date - TimeDelta::days(date.weekday().num_days_from_monday().into());
+    (monday, monday + TimeDelta::days(6))
+}
+
+/// First and last day of the month `date` falls in.
+pub fn month_of(date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let first = date.with_day(1).expect("every month has a first day");
+    let next_month = first
+        .checked_add_months(Months::new(1))
+        .expect("date out of range");
+    (first, next_month.pred_opt().expect("date out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(day, "%Y-%m-%d").expect("valid day")
+    }
+
+    /// A Wednesday.
+    fn today() -> NaiveDate {
+        date("2024-02-07")
+    }
+
+    fn parse(input: &str) -> Option<NaiveDate> {
+        parse_day(input, "%m/%d/%y", today())
+    }
+
+    #[test]
+    fn parses_named_days() {
+        assert_eq!(parse("today"), Some(today()));
+        assert_eq!(parse(" Yesterday "), Some(date("2024-02-06")));
+    }
+
+    #[test]
+    fn parses_weekdays_as_the_most_recent_one() {
+        assert_eq!(parse("monday"), Some(date("2024-02-05")));
+        assert_eq!(parse("Mon"), Some(date("2024-02-05")));
+        assert_eq!(parse("wednesday"), Some(today()));
+        assert_eq!(parse("thu"), Some(date("2024-02-01")));
+    }
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(parse("-3d"), Some(date("2024-02-04")));
+        assert_eq!(parse("-2w"), Some(date("2024-01-24")));
+        assert_eq!(parse("-0d"), Some(today()));
+        assert_eq!(parse("-3m"), None);
+        assert_eq!(parse("-d"), None);
+        assert_eq!(parse("-é"), None);
+        assert_eq!(parse("-3é"), None);
+        assert_eq!(parse("-éd"), None);
+        assert_eq!(parse("-2000000000000000000w"), None);
+        assert_eq!(parse("-9999999999d"), None);
+    }
+
+    #[test]
+    fn parses_configured_and_iso_dates() {
+        assert_eq!(parse("02/01/24"), Some(date("2024-02-01")));
+        assert_eq!(parse("2024-02-01"), Some(date("2024-02-01")));
+        assert_eq!(parse("2024-02-30"), None);
+        assert_eq!(parse("someday"), None);
+    }
+}
@@ -0,0 +1,215 @@
+use chrono::TimeDelta;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Sub},
+    str::FromStr,
+};
+
+/// An amount of tracked time with one-second resolution.
+///
+/// Stored and displayed as "HH:MM:SS", where the hour field keeps growing past 99 instead of
+/// wrapping, so it reads the same as the strings the data file has always contained.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TrackedTime(i64);
+
+impl TrackedTime {
+    pub const ZERO: TrackedTime = TrackedTime(0);
+
+    pub fn from_seconds(seconds: i64) -> Self {
+        TrackedTime(seconds)
+    }
+
+    pub fn seconds(&self) -> i64 {
+        self.0
+    }
+
+    /// Reads a duration the way people type it: "1h30m", "45m", "1.5h", "90s", "1:30" (hours
+    /// and minutes) or "01:30:00". Durations too long for `to_delta` are rejected.
+    pub fn parse_human(input: &str) -> Option<TrackedTime> {
+        let input = input.trim().to_lowercase();
+        let time = if input.contains(':') {
+            input
+                .parse()
+                .or_else(|_| format!("{}:00", input).parse())
+                .ok()?
+        } else {
+            let mut seconds = 0.0;
+            let mut number = String::new();
+            for c in input.chars().filter(|c| !c.is_whitespace()) {
+                let unit = match c {
+                    'h' => 3600.0,
+                    'm' => 60.0,
+                    's' => 1.0,
+                    // Only plain numbers, so "1h-30m" isn't read as 30 minutes.
+                    '0'..='9' | '.' => {
+                        number.push(c);
+                        continue;
+                    }
+                    _ => return None,
+                };
+                seconds += number.parse::<f64>().ok()? * unit;
+                number.clear();
+            }
+            if !number.is_empty() || input.is_empty() || !seconds.is_finite() {
+                return None;
+            }
+            // Saturates for durations out of range, which `to_delta` then turns down.
+            TrackedTime(seconds.round() as i64)
+        };
+        time.to_delta().map(|_| time)
+    }
+
+    /// The same amount of time as a `TimeDelta`, or `None` if it's more than that can hold.
+    pub fn to_delta(self) -> Option<TimeDelta> {
+        TimeDelta::try_seconds(self.0)
+    }
+}
+
+impl From<TimeDelta> for TrackedTime {
+    fn from(delta: TimeDelta) -> Self {
+        TrackedTime(delta.num_seconds())
+    }
+}
+
+impl fmt::Display for TrackedTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let seconds = self.0.abs();
+        write!(
+            f,
+            "{}{:02}:{:02}:{:02}",
+            sign,
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTrackedTimeError(String);
+
+impl fmt::Display for ParseTrackedTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid duration {:?}, expected \"HH:MM:SS\"", self.0)
+    }
+}
+
+impl std::error::Error for ParseTrackedTimeError {}
+
+impl FromStr for TrackedTime {
+    type Err = ParseTrackedTimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseTrackedTimeError(s.to_string());
+        let mut parts = s.trim().split(':').map(|part| part.trim().parse::<i64>());
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(h)), Some(Ok(m)), Some(Ok(s)), None)
+                if h >= 0 && (0..60).contains(&m) && (0..60).contains(&s) =>
+            {
+                h.checked_mul(3600)
+                    .and_then(|seconds| seconds.checked_add(m * 60 + s))
+                    .map(TrackedTime)
+                    .ok_or_else(err)
+            }
+            _ => Err(err()),
+        }
+    }
+}
+
+impl Serialize for TrackedTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TrackedTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Add for TrackedTime {
+    type Output = TrackedTime;
+
+    fn add(self, rhs: TrackedTime) -> TrackedTime {
+        TrackedTime(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for TrackedTime {
+    fn add_assign(&mut self, rhs: TrackedTime) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for TrackedTime {
+    type Output = TrackedTime;
+
+    fn sub(self, rhs: TrackedTime) -> TrackedTime {
+        TrackedTime(self.0 - rhs.0)
+    }
+}
+
+impl Sum for TrackedTime {
+    fn sum<I: Iterator<Item = TrackedTime>>(iter: I) -> Self {
+        iter.fold(TrackedTime::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a TrackedTime> for TrackedTime {
+    fn sum<I: Iterator<Item = &'a TrackedTime>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Option<i64> {
+        TrackedTime::parse_human(input).map(|time| time.seconds())
+    }
+
+    #[test]
+    fn parses_human_durations() {
+        assert_eq!(parse("1h30m"), Some(5400));
+        assert_eq!(parse("45m"), Some(2700));
+        assert_eq!(parse("1.5h"), Some(5400));
+        assert_eq!(parse(" 1H 30M 15s "), Some(5415));
+        assert_eq!(parse("90s"), Some(90));
+        assert_eq!(parse("1:30"), Some(5400));
+        assert_eq!(parse("01:30:00"), Some(5400));
+    }
+
+    #[test]
+    fn rejects_signs_and_garbage() {
+        assert_eq!(parse("1h-30m"), None);
+        assert_eq!(parse("-1h"), None);
+        assert_eq!(parse("+1h"), None);
+        assert_eq!(parse("1e3s"), None);
+        assert_eq!(parse("infh"), None);
+        assert_eq!(parse("1h30"), None);
+        assert_eq!(parse("h"), None);
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("1:75"), None);
+    }
+
+    #[test]
+    fn rejects_durations_out_of_range() {
+        assert_eq!(parse("3000000000000h"), None);
+        assert_eq!(parse("3000000000000:00"), None);
+        assert_eq!(parse("99999999999999999999:00:00"), None);
+        assert!("9999999999999999:00:00".parse::<TrackedTime>().is_err());
+    }
+
+    #[test]
+    fn displays_hours_past_99() {
+        assert_eq!(TrackedTime::from_seconds(360_000).to_string(), "100:00:00");
+        assert_eq!(TrackedTime::from_seconds(-61).to_string(), "-00:01:01");
+        assert_eq!("100:00:00".parse(), Ok(TrackedTime::from_seconds(360_000)));
+    }
+}
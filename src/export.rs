@@ -0,0 +1,97 @@
+use crate::{duration::TrackedTime, Project, ProjectFilter, TimeTracking, DAY_KEY_FORMAT};
+use chrono::NaiveDate;
+use std::io;
+
+/// Which rows `export_csv` writes and which projects and days they cover.
+pub struct ExportFilter {
+    /// Projects to include; all of them when empty.
+    pub projects: Vec<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// One row per recorded entry and day instead of one per project and day.
+    pub entries: bool,
+    pub project_filter: ProjectFilter,
+}
+
+impl ExportFilter {
+    fn includes_project(&self, key: &str, project: Option<&Project>) -> bool {
+        (self.projects.is_empty() || self.projects.iter().any(|name| name == key))
+            && project.is_some_and(|project| self.project_filter.matches(project))
+    }
+
+    fn includes_day(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+impl TimeTracking {
+    /// Writes tracked time as CSV. Dates and times are ISO 8601 and every duration is given
+    /// both in seconds and as "HH:MM:SS", so spreadsheets can sum the former.
+    pub fn export_csv<W: io::Write>(&self, writer: W, filter: &ExportFilter) -> csv::Result<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+        if filter.entries {
+            csv.write_record([
+                "project",
+                "date",
+                "start",
+                "end",
+                "duration_seconds",
+                "duration",
+                "note",
+            ])?;
+            let mut entries: Vec<_> = self
+                .entries
+                .iter()
+                .filter(|entry| {
+                    filter.includes_project(&entry.project, self.projects.get(&entry.project))
+                })
+                .collect();
+            entries.sort_by_key(|entry| entry.start);
+            for entry in entries {
+                // An entry that runs past midnight gets one row per day it touches.
+                for (date, time) in entry.time_per_day() {
+                    if !filter.includes_day(date) {
+                        continue;
+                    }
+                    let time = TrackedTime::from(time);
+                    csv.write_record([
+                        entry.project.clone(),
+                        date.format(DAY_KEY_FORMAT).to_string(),
+                        entry.start.to_rfc3339(),
+                        entry.end.to_rfc3339(),
+                        time.seconds().to_string(),
+                        time.to_string(),
+                        entry.note.clone().unwrap_or_default(),
+                    ])?;
+                }
+            }
+        } else {
+            csv.write_record(["project", "date", "duration_seconds", "duration"])?;
+            let mut rows = Vec::new();
+            for (key, project) in self.projects.iter() {
+                if !filter.includes_project(key, Some(project)) {
+                    continue;
+                }
+                for (day, time) in project.hours_per_day.iter() {
+                    let Ok(date) = NaiveDate::parse_from_str(day, DAY_KEY_FORMAT) else {
+                        continue;
+                    };
+                    if filter.includes_day(date) {
+                        rows.push((date, key.clone(), *time));
+                    }
+                }
+            }
+            rows.sort();
+            for (date, project, time) in rows {
+                csv.write_record([
+                    project,
+                    date.format(DAY_KEY_FORMAT).to_string(),
+                    time.seconds().to_string(),
+                    time.to_string(),
+                ])?;
+            }
+        }
+        csv.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{
+    dates,
+    duration::TrackedTime,
+    output::{print_json, print_plain, GoalJson, GoalsJson, Output},
+    print_box, TimeTracking, DAY_KEY_FORMAT,
+};
+use chrono::{Local, NaiveDate, TimeDelta};
+use crossterm::style::Stylize;
+use std::collections::BTreeMap;
+
+/// Width of the progress bars, in characters.
+const BAR_WIDTH: usize = 20;
+
+/// A bar filled in proportion to `time` out of `goal`, followed by the percentage.
+pub fn progress_bar(time: TrackedTime, goal: TrackedTime, width: usize) -> String {
+    let percent = if goal.seconds() > 0 {
+        time.seconds() * 100 / goal.seconds()
+    } else {
+        100
+    };
+    let filled = (percent.clamp(0, 100) as usize * width) / 100;
+    format!(
+        "[{}{}] {}%",
+        "█".repeat(filled),
+        "░".repeat(width - filled),
+        percent
+    )
+}
+
+impl TimeTracking {
+    /// Time tracked on each day across every project, including the running session.
+    fn tracked_per_day(&self) -> BTreeMap<NaiveDate, TrackedTime> {
+        let mut per_day: BTreeMap<NaiveDate, TrackedTime> = BTreeMap::new();
+        for project in self.projects.values() {
+            for (day, time) in project.hours_per_day.iter() {
+                if let Ok(date) = NaiveDate::parse_from_str(day, DAY_KEY_FORMAT) {
+                    *per_day.entry(date).or_default() += *time;
+                }
+            }
+        }
+        if let Some(ref session) = self.current_session {
+            for (date, time) in session.to_entry(Local::now()).time_per_day() {
+                *per_day.entry(date).or_default() += time.into();
+            }
+        }
+        per_day
+    }
+
+    /// Time tracked today across every project, including the running session.
+    pub fn tracked_today(&self) -> TrackedTime {
+        self.tracked_per_day()
+            .get(&Local::now().date_naive())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Progress towards the daily goal for the `start` dashboard, if there is one.
+    pub fn daily_progress(&self) -> Option<String> {
+        let goal = self.config.goals.daily()?;
+        Some(format!(
+            "Today: {}",
+            progress_bar(self.tracked_today(), goal, 10)
+        ))
+    }
+
+    /// Displays progress towards the daily and weekly goals from the config, and the streaks
+    /// of consecutive days that met the daily goal.
+    pub fn goals(&self) {
+        let (daily, weekly) = (self.config.goals.daily(), self.config.goals.weekly());
+        if daily.is_none() && weekly.is_none() && self.output == Output::Table {
+            println!(
+                "{}",
+                "\u{26a0} No goals set. Use `config set goals.daily 6h` or `config set goals.weekly 30h`."
+                    .with(self.config.colors.warning())
+            );
+            return;
+        }
+        let per_day = self.tracked_per_day();
+        let today = Local::now().date_naive();
+        let today_tracked = per_day.get(&today).copied().unwrap_or_default();
+        let (monday, sunday) = dates::week_of(today);
+        let week_tracked: TrackedTime = per_day.range(monday..=sunday).map(|(_, time)| time).sum();
+        let streaks = daily.map(|goal| streaks(&per_day, goal, today));
+
+        match self.output {
+            Output::Json => {
+                let goal = |goal: Option<TrackedTime>, tracked: TrackedTime| {
+                    goal.map(|goal| GoalJson {
+                        goal_seconds: goal.seconds(),
+                        tracked_seconds: tracked.seconds(),
+                    })
+                };
+                print_json(&GoalsJson {
+                    daily: goal(daily, today_tracked),
+                    weekly: goal(weekly, week_tracked),
+                    current_streak_days: streaks.map(|(current, _)| current),
+                    longest_streak_days: streaks.map(|(_, longest)| longest),
+                });
+                return;
+            }
+            Output::Plain => {
+                for (label, goal, tracked) in [
+                    ("daily", daily, today_tracked),
+                    ("weekly", weekly, week_tracked),
+                ] {
+                    if let Some(goal) = goal {
+                        print_plain(&[label, &tracked.to_string(), &goal.to_string()]);
+                    }
+                }
+                if let Some((current, longest)) = streaks {
+                    print_plain(&[
+                        String::from("streak"),
+                        current.to_string(),
+                        longest.to_string(),
+                    ]);
+                }
+                return;
+            }
+            Output::Table => {}
+        }
+
+        let mut lines = Vec::new();
+        if let Some(goal) = daily {
+            lines.push((
+                String::from("  ├─"),
+                String::from("Today"),
+                Some(format!(
+                    "{} of {} {}",
+                    today_tracked,
+                    goal,
+                    progress_bar(today_tracked, goal, BAR_WIDTH)
+                )),
+            ));
+        }
+        if let Some(goal) = weekly {
+            lines.push((
+                String::from("  ├─"),
+                String::from("This Week"),
+                Some(format!(
+                    "{} of {} {}",
+                    week_tracked,
+                    goal,
+                    progress_bar(week_tracked, goal, BAR_WIDTH)
+                )),
+            ));
+        }
+        if let Some((current, longest)) = streaks {
+            let days = |count: u32| format!("{} day{}", count, if count == 1 { "" } else { "s" });
+            lines.push((
+                String::from("  ├─"),
+                String::from("Current Streak"),
+                Some(days(current)),
+            ));
+            lines.push((
+                String::from("  ├─"),
+                String::from("Longest Streak"),
+                Some(days(longest)),
+            ));
+        }
+        if let Some(last) = lines.last_mut() {
+            last.0 = String::from("  ╰─");
+        }
+        print_box("Goals", &lines);
+    }
+}
+
+/// The current and the longest runs of consecutive days on which `per_day` reached `goal`.
+fn streaks(
+    per_day: &BTreeMap<NaiveDate, TrackedTime>,
+    goal: TrackedTime,
+    today: NaiveDate,
+) -> (u32, u32) {
+    let met = |date: &NaiveDate| per_day.get(date).is_some_and(|time| *time >= goal);
+    // Today still counts towards the streak until it's over.
+    let mut day = if met(&today) {
+        today
+    } else {
+        today - TimeDelta::days(1)
+    };
+    let mut current = 0;
+    while met(&day) {
+        current += 1;
+        day -= TimeDelta::days(1);
+    }
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for date in per_day.keys().filter(|date| met(date)) {
+        run = match previous {
+            Some(previous) if *date - previous == TimeDelta::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*date);
+    }
+    (current, longest)
+}
@@ -0,0 +1,364 @@
+use crate::{
+    config,
+    duration::TrackedTime,
+    tui::{Dashboard, Dialog},
+    Project, TimeEntry, TimeTracking, START_DATE_FORMAT,
+};
+use chrono::{DateTime, Local, TimeDelta};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use std::{
+    cmp, fs,
+    io::Read,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+/// How often the idle command is run and the idle file checked.
+const SOURCE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long the idle command may run before it is killed.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Watches for the user going away during the `start` loop, pausing the session when they do
+/// and asking what to do with the time away once they are back.
+pub struct IdleWatch {
+    after: TimeDelta,
+    command: Option<String>,
+    file: Option<PathBuf>,
+    last_key: DateTime<Local>,
+    /// Latest activity the idle command reported.
+    from_command: Option<DateTime<Local>>,
+    /// Latest activity the idle file reported.
+    from_file: Option<DateTime<Local>>,
+    /// The idle command while it runs, with when it was started. It runs alongside the loop so
+    /// that a slow command doesn't hold up redraws and checkpoints.
+    running: Option<(Child, Instant)>,
+    last_poll: Option<Instant>,
+    /// Whether a failing idle source was already reported, so that it is only reported once.
+    source_failed: bool,
+    /// Start of the pause put in when the user went away.
+    paused_at: Option<DateTime<Local>>,
+    /// Time away that the user hasn't decided about yet.
+    away: Option<(DateTime<Local>, DateTime<Local>)>,
+}
+
+impl IdleWatch {
+    /// A watch following `config`, or `None` if idle detection is off.
+    pub fn new(config: &config::Idle) -> Option<Self> {
+        Some(IdleWatch {
+            after: config.after()?.to_delta()?,
+            command: config.command.clone(),
+            file: config.file.clone(),
+            last_key: Local::now(),
+            from_command: None,
+            from_file: None,
+            running: None,
+            last_poll: None,
+            source_failed: false,
+            paused_at: None,
+            away: None,
+        })
+    }
+
+    /// The question about the time away, while there is one to answer with `keys`.
+    pub fn question(&self, keys: &config::Keys) -> Option<String> {
+        let (since, back) = self.away?;
+        Some(format!(
+            "\u{26a0} Away {}-{} ({}): [{}] keep it, [{}] discard it or [{}] give it to another project",
+            since.format("%H:%M"),
+            back.format("%H:%M"),
+            TrackedTime::from(back - since),
+            keys.keep,
+            keys.discard,
+            keys.give
+        ))
+    }
+
+    /// Starts the idle command unless it is off or still running.
+    fn start_command(&mut self) -> Result<(), String> {
+        let Some(ref command) = self.command else {
+            return Ok(());
+        };
+        if self.running.is_some() {
+            return Ok(());
+        }
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Idle command {:?} failed: {}", command, e))?;
+        self.running = Some((child, Instant::now()));
+        Ok(())
+    }
+
+    /// Reads what the idle command printed once it has exited, killing it if it takes too long.
+    fn finish_command(&mut self) -> Result<(), String> {
+        let (Some(command), Some((mut child, started))) = (&self.command, self.running.take())
+        else {
+            return Ok(());
+        };
+        let status = match child.try_wait() {
+            Ok(Some(status)) => status,
+            Ok(None) if started.elapsed() < COMMAND_TIMEOUT => {
+                self.running = Some((child, started));
+                return Ok(());
+            }
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "Idle command {:?} took longer than {}s",
+                    command,
+                    COMMAND_TIMEOUT.as_secs()
+                ));
+            }
+            Err(e) => return Err(format!("Idle command {:?} failed: {}", command, e)),
+        };
+        let mut stdout = String::new();
+        if let Some(mut pipe) = child.stdout.take() {
+            let _ = pipe.read_to_string(&mut stdout);
+        }
+        let idle_ms = stdout
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|_| status.success())
+            .ok_or_else(|| {
+                format!(
+                    "Idle command {:?} didn't print milliseconds of idle time",
+                    command
+                )
+            })?;
+        self.from_command = Some(Local::now() - TimeDelta::milliseconds(idle_ms));
+        Ok(())
+    }
+
+    fn poll_file(&mut self) -> Result<(), String> {
+        if let Some(ref file) = self.file {
+            let modified = fs::metadata(file)
+                .and_then(|metadata| metadata.modified())
+                .map_err(|e| format!("Idle file {:?} can't be read: {}", file, e))?;
+            self.from_file = Some(DateTime::<Local>::from(modified));
+        }
+        Ok(())
+    }
+
+    /// When the user was last active, from key presses and the idle sources.
+    fn last_activity(&mut self, tt: &TimeTracking) -> DateTime<Local> {
+        let mut result = self.finish_command();
+        if self
+            .last_poll
+            .is_none_or(|last_poll| last_poll.elapsed() >= SOURCE_INTERVAL)
+        {
+            self.last_poll = Some(Instant::now());
+            result = result.and(self.start_command()).and(self.poll_file());
+        }
+        if let Err(e) = result {
+            if !self.source_failed {
+                self.source_failed = true;
+                tt.info(&format!("\u{26a0} {}.", e), tt.config.colors.warning());
+            }
+        }
+        [self.from_command, self.from_file]
+            .into_iter()
+            .flatten()
+            .fold(self.last_key, cmp::max)
+    }
+
+    /// Pauses the session once the user has been away long enough, and notices when they come
+    /// back. Called on every turn of the `start` loop.
+    pub fn check(&mut self, tt: &mut TimeTracking) {
+        let Some(session) = tt.current_session.as_ref() else {
+            return;
+        };
+        let activity = self.last_activity(tt);
+        match self.paused_at {
+            Some(since) => {
+                // Resuming from another shell counts as coming back too.
+                let still_paused = session.is_paused()
+                    && session
+                        .pauses
+                        .last()
+                        .is_some_and(|pause| pause.start == since);
+                // Idle commands report durations measured a moment before they exit, so only
+                // activity clearly after the pause started counts as coming back.
+                if !still_paused || activity - since > TimeDelta::seconds(1) {
+                    self.come_back(tt, since, cmp::min(activity, Local::now()));
+                }
+            }
+            None if !session.is_paused() && Local::now() - activity >= self.after => {
+                let since = session
+                    .pauses
+                    .iter()
+                    .filter_map(|pause| pause.end)
+                    .chain([session.start, activity])
+                    .max()
+                    .expect("not empty");
+                tt.pause_idle(since);
+                self.paused_at = Some(since);
+            }
+            None => {}
+        }
+    }
+
+    fn come_back(&mut self, tt: &mut TimeTracking, since: DateTime<Local>, at: DateTime<Local>) {
+        let back = tt.resume_from_idle(since, at);
+        self.paused_at = None;
+        self.away = Some((since, back));
+    }
+
+    /// Takes a key press first, as activity and, unless a dialog is open, as the answer to the
+    /// question about the time away. Returns whether it was used up here, in which case the
+    /// `start` loop ignores it.
+    pub fn handle_key(
+        &mut self,
+        tt: &mut TimeTracking,
+        dashboard: &mut Dashboard,
+        key: KeyEvent,
+    ) -> bool {
+        if key.kind != KeyEventKind::Press {
+            return false;
+        }
+        self.last_key = Local::now();
+        // The key that wakes the tracker up only announces the user is back.
+        if let Some(since) = self.paused_at {
+            self.come_back(tt, since, self.last_key);
+            return true;
+        }
+        let Some((since, back)) = self.away.filter(|_| !dashboard.dialog_open()) else {
+            return false;
+        };
+        let KeyCode::Char(c) = key.code else {
+            return false;
+        };
+        match c {
+            c if c == tt.config.keys.keep => tt.keep_idle_time(since, back),
+            c if c == tt.config.keys.discard => tt.info(
+                &format!(
+                    "\u{2714} Discarded {} away.",
+                    TrackedTime::from(back - since)
+                ),
+                tt.config.colors.success(),
+            ),
+            // The question stays up until a project is picked, see `give_away`.
+            c if c == tt.config.keys.give => {
+                dashboard.pick_project(tt, Dialog::GiveIdleTime);
+                return true;
+            }
+            _ => return false,
+        }
+        self.away = None;
+        true
+    }
+
+    /// Records the time away on `project`, picked after the question about it.
+    pub fn give_away(&mut self, tt: &mut TimeTracking, project: &str) {
+        if let Some((since, back)) = self.away.take() {
+            tt.give_idle_time(since, back, project);
+        }
+    }
+}
+
+impl Drop for IdleWatch {
+    fn drop(&mut self) {
+        if let Some((mut child, _)) = self.running.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl TimeTracking {
+    /// Pauses the running session as of `since`, when the user was last active.
+    fn pause_idle(&mut self, since: DateTime<Local>) {
+        let _lock = self.lock().expect("unable to lock data file");
+        if let Some(ref mut session) = self.current_session {
+            if !session.is_paused() {
+                session.pause(since);
+                self.info(
+                    &format!(
+                        "\u{26a0} Nothing happened since {}, so the session is paused. Press any key when you're back.",
+                        since.format("%H:%M")
+                    ),
+                    self.config.colors.warning(),
+                );
+                self.save_to_file().expect("unable to save to file");
+            }
+        }
+    }
+
+    /// Ends the pause `pause_idle` put in at `since` as of `at`, when the user was first seen
+    /// again, unless it has already ended. Returns when it ended.
+    fn resume_from_idle(&mut self, since: DateTime<Local>, at: DateTime<Local>) -> DateTime<Local> {
+        let _lock = self.lock().expect("unable to lock data file");
+        if let Some(ref mut session) = self.current_session {
+            if let Some(pause) = session.pauses.last_mut() {
+                if pause.start == since {
+                    let back = *pause.end.get_or_insert(cmp::max(at, since));
+                    self.save_to_file().expect("unable to save to file");
+                    return back;
+                }
+            }
+        }
+        Local::now()
+    }
+
+    /// Counts the time away from `since` to `back` towards the running session after all, by
+    /// removing the pause it was excluded with.
+    fn keep_idle_time(&mut self, since: DateTime<Local>, back: DateTime<Local>) {
+        let _lock = self.lock().expect("unable to lock data file");
+        let Some(ref mut session) = self.current_session else {
+            return;
+        };
+        let pauses = session.pauses.len();
+        session.pauses.retain(|pause| pause.start != since);
+        if session.pauses.len() == pauses {
+            self.info(
+                "\u{26a0} The session that time was away from has ended.",
+                self.config.colors.warning(),
+            );
+            return;
+        }
+        let message = format!(
+            "\u{2714} Kept {} away on project {:?}.",
+            TrackedTime::from(back - since),
+            session.project
+        );
+        self.info(&message, self.config.colors.success());
+        self.save_to_file().expect("unable to save to file");
+    }
+
+    /// Records the time away from `since` to `back` on `project` instead, creating it if needed.
+    fn give_idle_time(&mut self, since: DateTime<Local>, back: DateTime<Local>, project: &str) {
+        let _lock = self.lock().expect("unable to lock data file");
+        let from = self
+            .current_session
+            .as_ref()
+            .map(|session| session.project.clone())
+            .unwrap_or_default();
+        self.projects
+            .entry(project.to_string())
+            .or_insert_with(|| Project::new(project, &since.format(START_DATE_FORMAT).to_string()));
+        self.entries.push(TimeEntry {
+            project: project.to_string(),
+            start: since,
+            end: back,
+            pauses: Vec::new(),
+            note: Some(format!("Time away from {:?}", from)),
+        });
+        self.recompute_totals();
+        self.info(
+            &format!(
+                "\u{2714} Gave {} away to project {:?}.",
+                TrackedTime::from(back - since),
+                project
+            ),
+            self.config.colors.success(),
+        );
+        self.save_to_file().expect("unable to save to file");
+    }
+}
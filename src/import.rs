@@ -0,0 +1,515 @@
+use crate::{
+    duration::TrackedTime, local_midnight, print_box, Project, TimeEntry, TimeTracking,
+    START_DATE_FORMAT,
+};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use crossterm::style::Stylize;
+use std::{collections::BTreeMap, io, path::Path};
+
+/// Names of the CSV columns an import reads each field of an entry from. Every entry needs a
+/// project, a start (a `start` date and time, or a `date` alone meaning midnight) and either
+/// an end or a duration.
+pub struct ColumnMap {
+    project: String,
+    note: Option<String>,
+    date: Option<String>,
+    start: Option<String>,
+    end_date: Option<String>,
+    end: Option<String>,
+    duration: Option<String>,
+}
+
+/// Fields `ColumnMap::parse` accepts.
+const FIELDS: &[&str] = &[
+    "project", "note", "date", "start", "end_date", "end", "duration",
+];
+
+impl ColumnMap {
+    /// Columns of Toggl Track's detailed report export.
+    pub fn toggl() -> Self {
+        ColumnMap {
+            project: String::from("Project"),
+            note: Some(String::from("Description")),
+            date: Some(String::from("Start date")),
+            start: Some(String::from("Start time")),
+            end_date: Some(String::from("End date")),
+            end: Some(String::from("End time")),
+            duration: Some(String::from("Duration")),
+        }
+    }
+
+    /// Columns of Clockify's detailed report export.
+    pub fn clockify() -> Self {
+        ColumnMap {
+            project: String::from("Project"),
+            note: Some(String::from("Description")),
+            date: Some(String::from("Start Date")),
+            start: Some(String::from("Start Time")),
+            end_date: Some(String::from("End Date")),
+            end: Some(String::from("End Time")),
+            duration: Some(String::from("Duration (h)")),
+        }
+    }
+
+    /// Reads a mapping such as `project=Client,start=Began,duration=Hours`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut fields = BTreeMap::new();
+        for pair in spec.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let Some((field, column)) = pair.split_once('=') else {
+                return Err(format!("expected FIELD=COLUMN, got {:?}", pair));
+            };
+            let field = field.trim();
+            if !FIELDS.contains(&field) {
+                return Err(format!(
+                    "unknown field {:?}, expected one of {}",
+                    field,
+                    FIELDS.join(", ")
+                ));
+            }
+            fields.insert(field, column.trim().to_string());
+        }
+        let columns = ColumnMap {
+            project: fields
+                .remove("project")
+                .ok_or("the mapping needs a project column")?,
+            note: fields.remove("note"),
+            date: fields.remove("date"),
+            start: fields.remove("start"),
+            end_date: fields.remove("end_date"),
+            end: fields.remove("end"),
+            duration: fields.remove("duration"),
+        };
+        if columns.start.is_none() && columns.date.is_none() {
+            return Err(String::from("the mapping needs a start or a date column"));
+        }
+        if columns.end.is_none() && columns.duration.is_none() {
+            return Err(String::from(
+                "the mapping needs an end or a duration column",
+            ));
+        }
+        Ok(columns)
+    }
+}
+
+/// Position of every mapped column in the file's header.
+struct Positions {
+    project: usize,
+    note: Option<usize>,
+    date: Option<usize>,
+    start: Option<usize>,
+    end_date: Option<usize>,
+    end: Option<usize>,
+    duration: Option<usize>,
+}
+
+impl Positions {
+    fn find(columns: &ColumnMap, headers: &csv::StringRecord) -> io::Result<Self> {
+        let find = |name: &String| {
+            headers
+                .iter()
+                .position(|header| {
+                    header
+                        .trim_start_matches('\u{feff}')
+                        .trim()
+                        .eq_ignore_ascii_case(name)
+                })
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("no column named {:?}", name),
+                    )
+                })
+        };
+        let optional = |name: &Option<String>| name.as_ref().map(find).transpose();
+        Ok(Positions {
+            project: find(&columns.project)?,
+            note: optional(&columns.note)?,
+            date: optional(&columns.date)?,
+            start: optional(&columns.start)?,
+            end_date: optional(&columns.end_date)?,
+            end: optional(&columns.end)?,
+            duration: optional(&columns.duration)?,
+        })
+    }
+}
+
+/// Reads days, times and durations the way the supported exports write them.
+struct RowParser<'a> {
+    date_formats: Vec<&'a str>,
+}
+
+impl RowParser<'_> {
+    fn date(&self, input: &str) -> Result<NaiveDate, String> {
+        self.date_formats
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(input, format).ok())
+            .ok_or_else(|| format!("unrecognized date {:?}", input))
+    }
+
+    fn time(&self, input: &str) -> Result<NaiveTime, String> {
+        ["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p"]
+            .iter()
+            .find_map(|format| NaiveTime::parse_from_str(input, format).ok())
+            .ok_or_else(|| format!("unrecognized time {:?}", input))
+    }
+
+    /// A full timestamp, either RFC 3339 or a date and a time separated by a space or "T".
+    fn date_time(&self, input: &str) -> Result<DateTime<Local>, String> {
+        if let Ok(date_time) = DateTime::parse_from_rfc3339(input) {
+            return Ok(date_time.with_timezone(&Local));
+        }
+        let (date, time) = input
+            .split_once([' ', 'T'])
+            .ok_or_else(|| format!("unrecognized date and time {:?}", input))?;
+        local(self.date(date)?.and_time(self.time(time.trim())?))
+    }
+
+    /// "HH:MM:SS", "HH:MM" or decimal hours such as "1.25".
+    fn duration(&self, input: &str) -> Result<TimeDelta, String> {
+        if let Ok(time) = input.parse::<TrackedTime>() {
+            return time
+                .to_delta()
+                .ok_or_else(|| format!("duration {:?} is too long", input));
+        }
+        if let Ok(time) = NaiveTime::parse_from_str(input, "%H:%M") {
+            return Ok(time - NaiveTime::MIN);
+        }
+        input
+            .parse::<f64>()
+            .ok()
+            .filter(|hours| hours.is_finite() && *hours >= 0.0)
+            .and_then(|hours| TimeDelta::try_seconds((hours * 3600.0).round() as i64))
+            .ok_or_else(|| format!("unrecognized duration {:?}", input))
+    }
+
+    fn entry(&self, at: &Positions, record: &csv::StringRecord) -> Result<TimeEntry, String> {
+        let field = |position: Option<usize>| {
+            position
+                .and_then(|position| record.get(position))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let project = field(Some(at.project)).ok_or("no project")?;
+        let date = field(at.date).map(|date| self.date(date)).transpose()?;
+        let start = match (date, field(at.start)) {
+            (Some(date), Some(time)) => local(date.and_time(self.time(time)?))?,
+            (Some(date), None) => local_midnight(date),
+            (None, Some(start)) => self.date_time(start)?,
+            (None, None) => return Err(String::from("no start")),
+        };
+        let end = match (field(at.end), field(at.duration)) {
+            (Some(end), _) => match (field(at.end_date), date) {
+                (Some(end_date), _) => local(self.date(end_date)?.and_time(self.time(end)?))?,
+                // Without an end date, an end time earlier than the start is on the next day.
+                (None, Some(date)) => {
+                    let time = self.time(end)?;
+                    let date = if time < start.time() {
+                        date.succ_opt().ok_or("date out of range")?
+                    } else {
+                        date
+                    };
+                    local(date.and_time(time))?
+                }
+                (None, None) => self.date_time(end)?,
+            },
+            (None, Some(duration)) => start
+                .checked_add_signed(self.duration(duration)?)
+                .ok_or_else(|| format!("duration {:?} is too long", duration))?,
+            (None, None) => return Err(String::from("no end or duration")),
+        };
+        if end < start {
+            return Err(String::from("ends before it starts"));
+        }
+        Ok(TimeEntry {
+            project: project.to_string(),
+            start,
+            end,
+            pauses: Vec::new(),
+            note: field(at.note).map(str::to_string),
+        })
+    }
+}
+
+fn plural(count: usize, one: &str, many: &str) -> String {
+    format!("{} {}", count, if count == 1 { one } else { many })
+}
+
+/// Splits off the entries already in `recorded` or earlier in `parsed`, comparing project,
+/// start and end, and counts them.
+fn leave_out_recorded(recorded: &[TimeEntry], parsed: Vec<TimeEntry>) -> (Vec<TimeEntry>, usize) {
+    let mut imported: Vec<TimeEntry> = Vec::new();
+    let mut duplicates = 0;
+    for entry in parsed {
+        if recorded.iter().chain(imported.iter()).any(|other| {
+            other.project == entry.project && other.start == entry.start && other.end == entry.end
+        }) {
+            duplicates += 1;
+        } else {
+            imported.push(entry);
+        }
+    }
+    (imported, duplicates)
+}
+
+fn local(date_time: NaiveDateTime) -> Result<DateTime<Local>, String> {
+    date_time
+        .and_local_timezone(Local)
+        .earliest()
+        .ok_or_else(|| format!("{} doesn't exist in local time", date_time))
+}
+
+impl TimeTracking {
+    /// Reads entries from a CSV export and adds them to the tracked time, creating projects as
+    /// needed. Rows matching an entry already recorded for the same project, start and end are
+    /// left out, so importing the same export twice changes nothing. With `dry_run`, only
+    /// prints what would change.
+    pub fn import(
+        &mut self,
+        path: &Path,
+        columns: &ColumnMap,
+        date_format: Option<&str>,
+        dry_run: bool,
+    ) -> io::Result<()> {
+        let _lock = self.lock()?;
+        let mut reader = csv::Reader::from_path(path)?;
+        let at = Positions::find(columns, reader.headers()?)?;
+        let config_format = self.config.date_format.clone();
+        let parser = RowParser {
+            date_formats: date_format
+                .into_iter()
+                .chain(["%Y-%m-%d", "%m/%d/%Y", &config_format])
+                .collect(),
+        };
+
+        let mut parsed = Vec::new();
+        let mut skipped = Vec::new();
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |position| position.line());
+            match parser.entry(&at, &record) {
+                Ok(entry) => parsed.push(entry),
+                Err(e) => skipped.push((line, e)),
+            }
+        }
+        let (imported, duplicates) = leave_out_recorded(&self.entries, parsed);
+
+        let mut per_project: BTreeMap<&str, (usize, TrackedTime)> = BTreeMap::new();
+        for entry in imported.iter() {
+            let (count, time) = per_project.entry(&entry.project).or_default();
+            *count += 1;
+            *time += entry.duration().into();
+        }
+        let verb = if dry_run { "Would import" } else { "Imported" };
+        if per_project.is_empty() {
+            println!(
+                "{}",
+                format!("\u{1f6c8} No new entries in {}.", path.display())
+                    .with(self.config.colors.info())
+            );
+        } else {
+            println!(
+                "{}",
+                format!(
+                    "\u{2714} {} {} from {}",
+                    verb,
+                    plural(imported.len(), "entry", "entries"),
+                    path.display()
+                )
+                .with(self.config.colors.success())
+            );
+            let lines: Vec<_> = per_project
+                .iter()
+                .enumerate()
+                .map(|(i, (project, (count, time)))| {
+                    let glyph = if i == per_project.len() - 1 {
+                        "  ╰─"
+                    } else {
+                        "  ├─"
+                    };
+                    let new = if self.projects.contains_key(*project) {
+                        ""
+                    } else {
+                        " (new)"
+                    };
+                    (
+                        String::from(glyph),
+                        format!("{}{}", project, new),
+                        Some(format!(
+                            "+{} in {}",
+                            time,
+                            plural(*count, "entry", "entries")
+                        )),
+                    )
+                })
+                .collect();
+            print_box(verb, &lines);
+        }
+        if duplicates > 0 {
+            println!(
+                "{}",
+                format!(
+                    "\u{1f6c8} Left out {} already recorded.",
+                    plural(duplicates, "row", "rows")
+                )
+                .with(self.config.colors.info())
+            );
+        }
+        for (line, reason) in skipped.iter() {
+            println!(
+                "{}",
+                format!("\u{26a0} Skipped line {}: {}", line, reason)
+                    .with(self.config.colors.warning())
+            );
+        }
+
+        if dry_run || imported.is_empty() {
+            return Ok(());
+        }
+        for entry in imported.iter() {
+            let start_date = entry.start.format(START_DATE_FORMAT).to_string();
+            let project = self
+                .projects
+                .entry(entry.project.clone())
+                .or_insert_with(|| Project::new(&entry.project, &start_date));
+            // History imported from elsewhere can predate the project here.
+            let earlier = NaiveDateTime::parse_from_str(&project.start_date, START_DATE_FORMAT)
+                .map_or(true, |current| entry.start.naive_local() < current);
+            if earlier {
+                project.start_date = start_date;
+            }
+        }
+        self.entries.extend(imported);
+        self.recompute_totals();
+        self.save_to_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(moment: &str) -> DateTime<Local> {
+        local(NaiveDateTime::parse_from_str(moment, "%Y-%m-%d %H:%M:%S").expect("valid moment"))
+            .expect("moment exists in local time")
+    }
+
+    fn parser() -> RowParser<'static> {
+        RowParser {
+            date_formats: vec!["%Y-%m-%d", "%m/%d/%Y"],
+        }
+    }
+
+    /// Parses the one row of `csv` the way an import would with `columns`.
+    fn entry(columns: &ColumnMap, csv: &str) -> Result<TimeEntry, String> {
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let at = Positions::find(columns, reader.headers().expect("headers")).expect("columns");
+        let record = reader.records().next().expect("a row").expect("valid row");
+        parser().entry(&at, &record)
+    }
+
+    #[test]
+    fn entry_from_toggl() {
+        let entry = entry(
+            &ColumnMap::toggl(),
+            "\u{feff}Project,Description,Start date,Start time,End date,End time,Duration\n\
+             Website,Landing page,2024-02-07,09:15:00,2024-02-07,11:45:30,02:30:30\n",
+        )
+        .expect("valid entry");
+        assert_eq!(entry.project, "Website");
+        assert_eq!(entry.note.as_deref(), Some("Landing page"));
+        assert_eq!(entry.start, at("2024-02-07 09:15:00"));
+        assert_eq!(entry.end, at("2024-02-07 11:45:30"));
+    }
+
+    #[test]
+    fn entry_from_clockify() {
+        let entry = entry(
+            &ColumnMap::clockify(),
+            "Project,Description,Start Date,Start Time,End Date,End Time,Duration (h)\n\
+             Website,,02/07/2024,09:15 AM,02/07/2024,01:30 PM,4.25\n",
+        )
+        .expect("valid entry");
+        assert_eq!(entry.note, None);
+        assert_eq!(entry.start, at("2024-02-07 09:15:00"));
+        assert_eq!(entry.end, at("2024-02-07 13:30:00"));
+    }
+
+    #[test]
+    fn entry_ending_after_midnight_without_end_date() {
+        let columns =
+            ColumnMap::parse("project=Project,date=Day,start=From,end=To").expect("valid");
+        let entry = entry(
+            &columns,
+            "Project,Day,From,To\nWebsite,2024-02-07,22:30,01:15\n",
+        )
+        .expect("valid entry");
+        assert_eq!(entry.start, at("2024-02-07 22:30:00"));
+        assert_eq!(entry.end, at("2024-02-08 01:15:00"));
+    }
+
+    #[test]
+    fn entry_with_decimal_hours() {
+        let columns =
+            ColumnMap::parse("project=Project,start=Began,duration=Hours").expect("valid");
+        let entry = entry(
+            &columns,
+            "Project,Began,Hours\nWebsite,2024-02-07T09:00:00,1.5\n",
+        )
+        .expect("valid entry");
+        assert_eq!(entry.start, at("2024-02-07 09:00:00"));
+        assert_eq!(entry.end, at("2024-02-07 10:30:00"));
+    }
+
+    #[test]
+    fn entry_rejects_ending_before_start() {
+        let columns = ColumnMap::toggl();
+        assert_eq!(
+            entry(
+                &columns,
+                "Project,Description,Start date,Start time,End date,End time,Duration\n\
+                 Website,,2024-02-07,11:00:00,2024-02-07,09:00:00,\n",
+            )
+            .map(|_| ()),
+            Err(String::from("ends before it starts"))
+        );
+    }
+
+    #[test]
+    fn duration_formats() {
+        let parser = parser();
+        assert_eq!(parser.duration("02:30:30"), Ok(TimeDelta::seconds(9030)));
+        assert_eq!(parser.duration("02:30"), Ok(TimeDelta::minutes(150)));
+        assert_eq!(parser.duration("1.25"), Ok(TimeDelta::minutes(75)));
+        assert_eq!(parser.duration("0"), Ok(TimeDelta::zero()));
+        for invalid in ["-1.5", "NaN", "inf", "1e300", "soon"] {
+            assert!(parser.duration(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn leave_out_recorded_on_reimport() {
+        let row = |project: &str, start: &str, end: &str| TimeEntry {
+            project: String::from(project),
+            start: at(start),
+            end: at(end),
+            pauses: Vec::new(),
+            note: None,
+        };
+        let export = || {
+            vec![
+                row("Website", "2024-02-07 09:00:00", "2024-02-07 10:00:00"),
+                row("Website", "2024-02-07 11:00:00", "2024-02-07 12:00:00"),
+                row("Website", "2024-02-07 11:00:00", "2024-02-07 12:00:00"),
+                row("Shop", "2024-02-07 11:00:00", "2024-02-07 12:00:00"),
+            ]
+        };
+
+        let (imported, duplicates) = leave_out_recorded(&[], export());
+        assert_eq!(imported.len(), 3);
+        assert_eq!(duplicates, 1);
+
+        let (again, duplicates) = leave_out_recorded(&imported, export());
+        assert!(again.is_empty());
+        assert_eq!(duplicates, 4);
+    }
+}
@@ -0,0 +1,377 @@
+use crate::{duration::TrackedTime, invalid, ProjectFilter, TimeTracking, DAY_KEY_FORMAT};
+use chrono::NaiveDate;
+use std::{fmt::Write, io};
+
+/// How `invoice` lays out its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvoiceFormat {
+    Text,
+    Markdown,
+    Html,
+}
+
+/// What goes on an invoice and how its amounts are computed.
+pub struct InvoiceOptions {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Projects to bill; every project matching `filter` when empty.
+    pub projects: Vec<String>,
+    pub filter: ProjectFilter,
+    /// Each day's time on a project is rounded up to a multiple of this many minutes.
+    pub rounding: Option<u32>,
+    /// Tax added on top of the subtotal, in percent.
+    pub tax: Option<f64>,
+    pub format: InvoiceFormat,
+}
+
+/// Time billed for one project on one day.
+struct Item {
+    date: NaiveDate,
+    project: String,
+    time: TrackedTime,
+    rate: f64,
+    /// In hundredths of the currency unit, so totals add up exactly.
+    amount: i64,
+}
+
+/// Everything an invoice shows, ready to be rendered in any `InvoiceFormat`.
+struct Invoice {
+    title: String,
+    period: String,
+    currency: String,
+    items: Vec<Item>,
+    subtotal: i64,
+    tax: Option<(f64, i64)>,
+}
+
+impl Invoice {
+    /// Can't overflow, `TimeTracking::invoice` only builds invoices whose total fits.
+    fn total(&self) -> i64 {
+        self.subtotal + self.tax.map_or(0, |(_, tax)| tax)
+    }
+
+    fn money(&self, cents: i64) -> String {
+        let sign = if cents < 0 { "-" } else { "" };
+        let cents = cents.unsigned_abs();
+        format!(
+            "{}{}.{:02} {}",
+            sign,
+            cents / 100,
+            cents % 100,
+            self.currency
+        )
+    }
+
+    fn rate(&self, rate: f64) -> String {
+        format!("{:.2} {}/h", rate, self.currency)
+    }
+
+    fn hours(time: TrackedTime) -> String {
+        format!("{:.2}", time.seconds() as f64 / 3600.0)
+    }
+
+    fn header() -> [&'static str; 6] {
+        ["Date", "Project", "Time", "Hours", "Rate", "Amount"]
+    }
+
+    fn rows(&self, date_format: &str) -> Vec<[String; 6]> {
+        self.items
+            .iter()
+            .map(|item| {
+                [
+                    item.date.format(date_format).to_string(),
+                    item.project.clone(),
+                    item.time.to_string(),
+                    Invoice::hours(item.time),
+                    self.rate(item.rate),
+                    self.money(item.amount),
+                ]
+            })
+            .collect()
+    }
+
+    /// Label and amount of the subtotal, tax and total lines.
+    fn totals(&self) -> Vec<(String, String)> {
+        let mut totals = Vec::new();
+        if let Some((percent, tax)) = self.tax {
+            totals.push((String::from("Subtotal"), self.money(self.subtotal)));
+            totals.push((format!("Tax ({}%)", percent), self.money(tax)));
+        }
+        totals.push((String::from("Total"), self.money(self.total())));
+        totals
+    }
+
+    fn text(&self, date_format: &str) -> String {
+        let rows = self.rows(date_format);
+        let totals = self.totals();
+        let mut widths = Invoice::header().map(str::len);
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for (label, amount) in totals.iter() {
+            widths[4] = widths[4].max(label.chars().count());
+            widths[5] = widths[5].max(amount.chars().count());
+        }
+        let line = |cells: [&str; 6]| {
+            let mut line = String::new();
+            for (i, (cell, width)) in cells.iter().zip(widths.iter()).enumerate() {
+                // Text columns are left aligned, numbers right aligned.
+                if i < 2 {
+                    write!(line, "{:<width$}  ", cell, width = width).unwrap();
+                } else {
+                    write!(line, "{:>width$}  ", cell, width = width).unwrap();
+                }
+            }
+            line.trim_end().to_string()
+        };
+
+        let mut out = format!("{}\n{}\n\n", self.title, self.period);
+        out.push_str(&line(Invoice::header()));
+        out.push('\n');
+        let rule = "─".repeat(widths.iter().sum::<usize>() + 2 * (widths.len() - 1));
+        out.push_str(&rule);
+        out.push('\n');
+        for row in rows.iter() {
+            out.push_str(&line(row.each_ref().map(String::as_str)));
+            out.push('\n');
+        }
+        out.push_str(&rule);
+        out.push('\n');
+        for (label, amount) in totals.iter() {
+            out.push_str(&line(["", "", "", "", label, amount]));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn markdown(&self, date_format: &str) -> String {
+        let escape = |cell: &str| cell.replace('|', "\\|");
+        let mut out = format!("# {}\n\n{}\n\n", self.title, self.period);
+        out.push_str(&format!("| {} |\n", Invoice::header().join(" | ")));
+        out.push_str("| --- | --- | ---: | ---: | ---: | ---: |\n");
+        for row in self.rows(date_format) {
+            let cells: Vec<_> = row.iter().map(|cell| escape(cell)).collect();
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+        for (label, amount) in self.totals() {
+            if label == "Total" {
+                out.push_str(&format!("| | | | | **{}** | **{}** |\n", label, amount));
+            } else {
+                out.push_str(&format!("| | | | | {} | {} |\n", label, amount));
+            }
+        }
+        out
+    }
+
+    fn html(&self, date_format: &str) -> String {
+        let escape = |text: &str| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\ntable {{ border-collapse: collapse; }}\nth, td {{ padding: 0.25em 0.75em; border-bottom: 1px solid #ccc; }}\n.number {{ text-align: right; }}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n<p>{period}</p>\n<table>\n<tr>",
+            title = escape(&self.title),
+            period = escape(&self.period)
+        );
+        for (i, cell) in Invoice::header().iter().enumerate() {
+            let class = if i < 2 { "" } else { " class=\"number\"" };
+            write!(out, "<th{}>{}</th>", class, cell).unwrap();
+        }
+        out.push_str("</tr>\n");
+        for row in self.rows(date_format) {
+            out.push_str("<tr>");
+            for (i, cell) in row.iter().enumerate() {
+                let class = if i < 2 { "" } else { " class=\"number\"" };
+                write!(out, "<td{}>{}</td>", class, escape(cell)).unwrap();
+            }
+            out.push_str("</tr>\n");
+        }
+        for (label, amount) in self.totals() {
+            let (open, close) = if label == "Total" {
+                ("<strong>", "</strong>")
+            } else {
+                ("", "")
+            };
+            writeln!(
+                out,
+                "<tr><td colspan=\"5\" class=\"number\">{open}{}{close}</td><td class=\"number\">{open}{}{close}</td></tr>",
+                escape(&label),
+                escape(&amount)
+            )
+            .unwrap();
+        }
+        out.push_str("</table>\n</body>\n</html>\n");
+        out
+    }
+}
+
+/// Rounds an amount to whole cents, or `None` if it doesn't fit.
+fn cents(amount: f64) -> Option<i64> {
+    (amount.is_finite() && amount.abs() < i64::MAX as f64).then(|| amount.round() as i64)
+}
+
+fn too_large() -> io::Error {
+    invalid(String::from("the amounts are too large to add up"))
+}
+
+impl TimeTracking {
+    /// Hourly rate and currency billed for project `key`: its own where set, otherwise its
+    /// client's, with the configured currency as the last resort.
+    fn billing(&self, key: &str) -> Option<(f64, String)> {
+        let project = self.projects.get(key)?;
+        let client = project
+            .client
+            .as_ref()
+            .and_then(|client| self.clients.get(client));
+        let rate = project
+            .rate
+            .or_else(|| client.and_then(|client| client.rate))?;
+        let currency = project
+            .currency
+            .clone()
+            .or_else(|| client.and_then(|client| client.currency.clone()))
+            .unwrap_or_else(|| self.config.currency.clone());
+        Some((rate, currency))
+    }
+
+    /// Renders an itemized invoice with one line per project and day tracked in the period.
+    /// Fails if a billed project has no rate or the projects bill in different currencies.
+    pub fn invoice(&self, options: &InvoiceOptions) -> io::Result<String> {
+        let mut keys: Vec<_> = self
+            .projects
+            .iter()
+            .filter(|(key, project)| {
+                (options.projects.is_empty() || options.projects.contains(key))
+                    && options.filter.matches(project)
+            })
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort();
+
+        let mut items = Vec::new();
+        let mut currencies = Vec::new();
+        for key in keys {
+            let mut days: Vec<_> = self.projects[key]
+                .hours_per_day
+                .iter()
+                .filter_map(|(day, time)| {
+                    let date = NaiveDate::parse_from_str(day, DAY_KEY_FORMAT).ok()?;
+                    (options.from..=options.to)
+                        .contains(&date)
+                        .then_some((date, *time))
+                })
+                .filter(|(_, time)| *time > TrackedTime::ZERO)
+                .collect();
+            if days.is_empty() {
+                continue;
+            }
+            days.sort();
+            let (rate, currency) = self.billing(key).ok_or_else(|| {
+                invalid(format!(
+                    "project {:?} has no hourly rate, set one with `rate {:?} <RATE>`",
+                    key, key
+                ))
+            })?;
+            if !currencies.contains(&currency) {
+                currencies.push(currency);
+            }
+            for (date, time) in days {
+                let time = match options.rounding.filter(|minutes| *minutes > 0) {
+                    Some(minutes) => {
+                        let step = i64::from(minutes) * 60;
+                        TrackedTime::from_seconds((time.seconds() + step - 1) / step * step)
+                    }
+                    None => time,
+                };
+                let amount = cents(rate * time.seconds() as f64 / 36.0).ok_or_else(too_large)?;
+                items.push(Item {
+                    date,
+                    project: self.projects[key].name.clone(),
+                    time,
+                    rate,
+                    amount,
+                });
+            }
+        }
+        if items.is_empty() {
+            return Err(invalid(String::from(
+                "nothing billable was tracked in that period",
+            )));
+        }
+        if currencies.len() > 1 {
+            return Err(invalid(format!(
+                "the projects bill in different currencies ({}), invoice them separately",
+                currencies.join(", ")
+            )));
+        }
+        items.sort_by(|a, b| (a.date, &a.project).cmp(&(b.date, &b.project)));
+
+        let subtotal = items
+            .iter()
+            .try_fold(0i64, |sum, item| sum.checked_add(item.amount))
+            .ok_or_else(too_large)?;
+        let tax = match options.tax {
+            Some(percent) => {
+                let tax = cents(subtotal as f64 * percent / 100.0)
+                    .filter(|tax| subtotal.checked_add(*tax).is_some())
+                    .ok_or_else(too_large)?;
+                Some((percent, tax))
+            }
+            None => None,
+        };
+        let invoice = Invoice {
+            title: match options.filter.client {
+                Some(ref client) => format!("Invoice for {}", client),
+                None => String::from("Invoice"),
+            },
+            period: format!(
+                "{} \u{2192} {}",
+                options.from.format(&self.config.date_format),
+                options.to.format(&self.config.date_format)
+            ),
+            currency: currencies.remove(0),
+            items,
+            subtotal,
+            tax,
+        };
+        Ok(match options.format {
+            InvoiceFormat::Text => invoice.text(&self.config.date_format),
+            InvoiceFormat::Markdown => invoice.markdown(&self.config.date_format),
+            InvoiceFormat::Html => invoice.html(&self.config.date_format),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn money_has_the_sign_in_front() {
+        let invoice = Invoice {
+            title: String::new(),
+            period: String::new(),
+            currency: String::from("USD"),
+            items: Vec::new(),
+            subtotal: 0,
+            tax: None,
+        };
+        assert_eq!(invoice.money(12345), "123.45 USD");
+        assert_eq!(invoice.money(5), "0.05 USD");
+        assert_eq!(invoice.money(-550), "-5.50 USD");
+        assert_eq!(invoice.money(-5), "-0.05 USD");
+    }
+
+    #[test]
+    fn cents_only_when_they_fit() {
+        assert_eq!(cents(1234.5), Some(1235));
+        assert_eq!(cents(-0.4), Some(0));
+        assert_eq!(cents(1e19), None);
+        assert_eq!(cents(f64::INFINITY), None);
+        assert_eq!(cents(f64::NAN), None);
+    }
+}
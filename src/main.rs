@@ -1,80 +1,207 @@
-use chrono::{Local, NaiveDate, NaiveDateTime, ParseError};
-use clap::{arg, Command};
+mod config;
+mod dates;
+mod duration;
+mod export;
+mod goals;
+mod idle;
+mod import;
+mod invoice;
+mod manual;
+mod output;
+mod picker;
+mod projects;
+mod status_bar;
+mod tui;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, ParseError, TimeDelta};
+use clap::{arg, ArgMatches, Command};
+use config::Config;
 use crossterm::{
-    cursor,
-    event::{poll, read, Event, KeyCode, KeyEvent},
-    execute,
-    style::{Color, Print, ResetColor, SetForegroundColor, Stylize},
-    terminal,
+    event::{poll, read, Event, KeyCode, KeyEvent, KeyModifiers},
+    style::{Color, Stylize},
+};
+use duration::TrackedTime;
+use idle::IdleWatch;
+use output::{
+    print_json, print_plain, DayJson, DayTotalJson, EntryJson, Output, ProjectDetailsJson,
+    ProjectJson, ProjectListJson, ReportJson, StatusJson, TotalJson,
 };
 use serde::{Deserialize, Serialize};
 use std::{
+    cell::RefCell,
     cmp,
-    collections::HashMap,
-    fs::{File, OpenOptions},
-    io::{self, stdout, Read, Write},
-    thread,
-    time::Duration,
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{self, stdout, IsTerminal, Read, Write},
+    panic,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
 };
+use tui::{Dashboard, Dialog};
 
-fn print_info(info: &str, t: bool, c: Color) {
-    let color = c;
-    if t {
-        execute!(
-            stdout(),
-            cursor::MoveUp(1),
-            terminal::Clear(terminal::ClearType::CurrentLine),
-            cursor::MoveToColumn(0),
-            SetForegroundColor(color),
-            Print(info),
-            ResetColor,
-            cursor::MoveDown(1),
-            cursor::MoveToColumn(0),
-        )
-        .expect("Failed to execute commands");
-    } else {
-        execute!(
-            stdout(),
-            cursor::MoveUp(1),
-            terminal::Clear(terminal::ClearType::CurrentLine),
-            cursor::MoveToColumn(0),
-            SetForegroundColor(color),
-            Print(info),
-            ResetColor,
-            cursor::MoveDown(1),
-            terminal::Clear(terminal::ClearType::CurrentLine),
-            cursor::MoveToColumn(0),
-        )
-        .expect("Failed to execute commands");
-    }
-}
-
-fn get_input(prompt: &str) -> String {
-    terminal::disable_raw_mode().expect("Failed to enable raw mode");
-    execute!(
-        stdout(),
-        cursor::MoveUp(1),
-        terminal::Clear(terminal::ClearType::CurrentLine),
-        cursor::MoveToColumn(0),
-        Print(prompt),
-    )
-    .expect("Failed to execute commands");
-    io::stdout().flush().expect("Failed to flush stdout");
+/// Format of the `hours_per_day` keys in the data file (ISO 8601).
+const DAY_KEY_FORMAT: &str = "%Y-%m-%d";
 
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read line");
-    terminal::enable_raw_mode().expect("Failed to enable raw mode");
-    input.trim().to_string()
+/// Format of `Project::start_date` in the data file (ISO 8601).
+const START_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Formats days and start dates were stored in before version 3 of the data file.
+const LEGACY_DAY_FORMAT: &str = "%m/%d/%y";
+const LEGACY_START_DATE_FORMAT: &str = "%m/%d/%y %H:%M:%S";
+
+/// Renders an `hours_per_day` key in the user's date format.
+fn display_day(day: &str, date_format: &str) -> String {
+    NaiveDate::parse_from_str(day, DAY_KEY_FORMAT)
+        .map(|date| date.format(date_format).to_string())
+        .unwrap_or_else(|_| day.to_string())
+}
+
+/// Padding computed for text `expected` characters wide, adjusted so the box border stays
+/// aligned when the actual `text` is wider or narrower.
+fn pad(width: usize, text: &str, expected: usize) -> String {
+    " ".repeat(cmp::max(
+        (width + expected).saturating_sub(text.chars().count()),
+        1,
+    ))
+}
+
+/// Draws a box in the style of `Project::display`: `title` on the first line, then one line
+/// per `(prefix, label, value)` with the tree glyphs in `prefix`.
+fn print_box(title: &str, lines: &[(String, String, Option<String>)]) {
+    let line_len = |(prefix, label, value): &(String, String, Option<String>)| {
+        prefix.chars().count()
+            + label.chars().count()
+            + value.as_ref().map_or(0, |value| value.chars().count() + 2)
+    };
+    let width = lines
+        .iter()
+        .map(line_len)
+        .chain([title.chars().count() + 2])
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    println!("╭{}╮", "─".repeat(width - 1));
+    println!(
+        "╰─{}{}│",
+        title.negative(),
+        " ".repeat(width - title.chars().count() - 2)
+    );
+    for line in lines {
+        let (prefix, label, value) = line;
+        let padding = " ".repeat(width - line_len(line));
+        match value {
+            Some(value) => println!(
+                "{}{}: {}{}│",
+                prefix,
+                label,
+                value.clone().italic(),
+                padding
+            ),
+            None => println!("{}{}{}│", prefix, label.clone().underlined(), padding),
+        }
+    }
+    println!("{}╯", "─".repeat(width));
+}
+
+/// Error for a command that can't be carried out as asked, e.g. on a missing project.
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Project {
     pub name: String,
     pub start_date: String,
-    pub hours_per_day: HashMap<String, String>,
-    pub total_time: String,
+    pub hours_per_day: HashMap<String, TrackedTime>,
+    pub total_time: TrackedTime,
+    /// Archived projects are left out of `list` unless asked for.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    /// Hourly rate billed by `invoice`, overriding the client's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(default, skip_serializing_if = "Budget::is_empty")]
+    pub budget: Budget,
+}
+
+/// Time allotted to a project, overall and for each week.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Budget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<TrackedTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weekly: Option<TrackedTime>,
+}
+
+impl Budget {
+    pub fn is_empty(&self) -> bool {
+        *self == Budget::default()
+    }
+}
+
+/// How much of one of a project's budgets is used.
+pub struct BudgetUsage {
+    pub label: &'static str,
+    pub used: TrackedTime,
+    pub budget: TrackedTime,
+}
+
+impl BudgetUsage {
+    pub fn percent(&self) -> i64 {
+        if self.budget.seconds() <= 0 {
+            return 100;
+        }
+        self.used.seconds() * 100 / self.budget.seconds()
+    }
+
+    /// The budget with what is left of it, or by how much it is overrun.
+    pub fn describe(&self) -> String {
+        if self.used > self.budget {
+            format!("{}, {} over", self.budget, self.used - self.budget)
+        } else {
+            format!("{}, {} left", self.budget, self.budget - self.used)
+        }
+    }
+}
+
+/// Billing defaults for every project of a client.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Client {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+/// Which projects a command covers, from the `--tag` and `--client` arguments added by
+/// `filter_args`.
+#[derive(Debug, Default, Clone)]
+pub struct ProjectFilter {
+    pub tags: Vec<String>,
+    pub client: Option<String>,
+}
+
+impl ProjectFilter {
+    /// Whether `project` has any of the tags and the client asked for, where given.
+    pub fn matches(&self, project: &Project) -> bool {
+        (self.tags.is_empty() || project.tags.iter().any(|tag| self.tags.contains(tag)))
+            && self
+                .client
+                .as_ref()
+                .is_none_or(|client| project.client.as_ref() == Some(client))
+    }
 }
 
 impl Project {
@@ -83,40 +210,138 @@ impl Project {
             name: name.to_string(),
             start_date: start_date.to_string(),
             hours_per_day: HashMap::new(),
-            total_time: String::from("00:00:00"),
+            total_time: TrackedTime::ZERO,
+            archived: false,
+            tags: Vec::new(),
+            client: None,
+            rate: None,
+            currency: None,
+            budget: Budget::default(),
+        }
+    }
+
+    /// Usage of each budget the project has, counting `unrecorded` time per day on top of
+    /// `hours_per_day`, e.g. that of a running session.
+    pub fn budget_usage(&self, unrecorded: &[(NaiveDate, TimeDelta)]) -> Vec<BudgetUsage> {
+        let mut usage = Vec::new();
+        if let Some(budget) = self.budget.total {
+            let extra: TimeDelta = unrecorded.iter().map(|(_, time)| *time).sum();
+            usage.push(BudgetUsage {
+                label: "Budget",
+                used: self.total_time + extra.into(),
+                budget,
+            });
+        }
+        if let Some(budget) = self.budget.weekly {
+            let (monday, sunday) = dates::week_of(Local::now().date_naive());
+            let this_week = |date: &NaiveDate| (monday..=sunday).contains(date);
+            let recorded: TrackedTime = self
+                .hours_per_day
+                .iter()
+                .filter(|(day, _)| {
+                    NaiveDate::parse_from_str(day, DAY_KEY_FORMAT)
+                        .is_ok_and(|date| this_week(&date))
+                })
+                .map(|(_, time)| *time)
+                .sum();
+            let extra: TimeDelta = unrecorded
+                .iter()
+                .filter(|(date, _)| this_week(date))
+                .map(|(_, time)| *time)
+                .sum();
+            usage.push(BudgetUsage {
+                label: "Weekly Budget",
+                used: recorded + extra.into(),
+                budget,
+            });
+        }
+        usage
+    }
+
+    /// Lines for the client, rate, budgets and tags between the start date and total time in
+    /// `display`, padded to the width of the start date line.
+    fn details(&self, width: usize) -> String {
+        let mut details = String::new();
+        if let Some(ref client) = self.client {
+            details.push_str(&format!(
+                "  ├─{}: {}{}│\n",
+                "Client".underlined(),
+                client.clone().italic(),
+                pad(width, client, 21)
+            ));
+        }
+        if let Some(rate) = self.rate {
+            let rate = format!("{:.2}/h", rate);
+            let rate = match self.currency {
+                Some(ref currency) => format!("{} {}", rate, currency),
+                None => rate,
+            };
+            details.push_str(&format!(
+                "  ├─{}: {}{}│\n",
+                "Rate".underlined(),
+                rate.clone().italic(),
+                pad(width, &rate, 23)
+            ));
+        }
+        for usage in self.budget_usage(&[]) {
+            let value = usage.describe();
+            details.push_str(&format!(
+                "  ├─{}: {}{}│\n",
+                usage.label.underlined(),
+                value.clone().italic(),
+                pad(width, &value, 27 - usage.label.len())
+            ));
+        }
+        if !self.tags.is_empty() {
+            let tags = self.tags.join(", ");
+            details.push_str(&format!(
+                "  ├─{}: {}{}│\n",
+                "Tags".underlined(),
+                tags.clone().italic(),
+                pad(width, &tags, 23)
+            ));
         }
+        details
     }
 
-    pub fn display(&mut self, last: bool, solo: bool, namelen: usize) {
+    pub fn display(&mut self, last: bool, solo: bool, namelen: usize, date_format: &str) {
+        let start_date = NaiveDateTime::parse_from_str(&self.start_date, START_DATE_FORMAT)
+            .map(|date| {
+                date.format(&format!("{} %H:%M:%S", date_format))
+                    .to_string()
+            })
+            .unwrap_or_else(|_| self.start_date.clone());
         if solo {
             println!("╭{}╮", "─".repeat(cmp::max(namelen, 33)));
             println!(
-                "╰─{}{}│\n  ├─{}: {}{}│\n  ╰─{}: {}{}│",
+                "╰─{}{}│\n  ├─{}: {}{}│\n{}  ╰─{}: {}{}│",
                 self.name.clone().negative(),
                 " ".repeat(35 - cmp::min(self.name.len() + 3, 34)),
                 "Start Date".underlined(),
-                self.start_date.clone().italic(),
-                " ".repeat(cmp::max(cmp::max(namelen, 32) - 32, 1)),
+                start_date.clone().italic(),
+                pad(cmp::max(cmp::max(namelen, 32) - 32, 1), &start_date, 17),
+                self.details(cmp::max(cmp::max(namelen, 32) - 32, 1)),
                 "Total Time".underlined(),
-                self.total_time.clone().italic(),
+                self.total_time.to_string().italic(),
                 " ".repeat(cmp::max(cmp::max(namelen, 23) - 23, 10))
             );
 
             let binding = self.order_hours_per_day().unwrap().clone();
             for (i, proj) in binding.iter().enumerate() {
+                let day = display_day(proj.0, date_format);
                 if i == binding.len() - 1 {
                     println!(
                         "    ╰─{}: {}{}│",
-                        proj.0,
-                        proj.1.clone().italic(),
-                        " ".repeat(cmp::max(cmp::max(namelen, 23) - 23, 10))
+                        day,
+                        proj.1.to_string().italic(),
+                        pad(cmp::max(cmp::max(namelen, 23) - 23, 10), &day, 8)
                     );
                 } else {
                     println!(
                         "    ├─{}: {}{}│",
-                        proj.0,
-                        proj.1.clone().italic(),
-                        " ".repeat(cmp::max(cmp::max(namelen, 23) - 23, 10))
+                        day,
+                        proj.1.to_string().italic(),
+                        pad(cmp::max(cmp::max(namelen, 23) - 23, 10), &day, 8)
                     );
                 }
             }
@@ -124,32 +349,34 @@ impl Project {
             println!("─{}╯", "─".repeat(cmp::max(namelen, 33)));
         } else if last {
             println!(
-                "╰─{}{}│\n  ├─{}: {}{}│\n  ╰─{}: {}{}│",
+                "╰─{}{}│\n  ├─{}: {}{}│\n{}  ╰─{}: {}{}│",
                 self.name.clone().negative(),
                 " ".repeat(namelen + 2 - cmp::min(self.name.len() + 3, namelen + 1)),
                 "Start Date".underlined(),
-                self.start_date.clone().italic(),
-                " ".repeat(cmp::max(cmp::max(namelen, 32) - 32, 10)),
+                start_date.clone().italic(),
+                pad(cmp::max(cmp::max(namelen, 32) - 32, 10), &start_date, 17),
+                self.details(cmp::max(cmp::max(namelen, 32) - 32, 10)),
                 "Total Time".underlined(),
-                self.total_time.clone().italic(),
+                self.total_time.to_string().italic(),
                 " ".repeat(cmp::max(cmp::max(namelen, 23) - 23, 10))
             );
 
             let binding = self.order_hours_per_day().unwrap().clone();
             for (i, proj) in binding.iter().enumerate() {
+                let day = display_day(proj.0, date_format);
                 if i == binding.len() - 1 {
                     println!(
                         "    ╰─{}: {}{}│",
-                        proj.0,
-                        proj.1.clone().italic(),
-                        " ".repeat(cmp::max(cmp::max(namelen, 23) - 23, 10))
+                        day,
+                        proj.1.to_string().italic(),
+                        pad(cmp::max(cmp::max(namelen, 23) - 23, 10), &day, 8)
                     );
                 } else {
                     println!(
                         "    ├─{}: {}{}│",
-                        proj.0,
-                        proj.1.clone().italic(),
-                        " ".repeat(cmp::max(cmp::max(namelen, 23) - 23, 10))
+                        day,
+                        proj.1.to_string().italic(),
+                        pad(cmp::max(cmp::max(namelen, 23) - 23, 10), &day, 8)
                     );
                 }
             }
@@ -157,45 +384,47 @@ impl Project {
             println!("─{}╯", "─".repeat(cmp::max(namelen, 34)));
         } else {
             println!(
-                "╰─{}{}│\n  ├─{}: {}{}│\n  ╰─{}: {}{}│",
+                "╰─{}{}│\n  ├─{}: {}{}│\n{}  ╰─{}: {}{}│",
                 self.name.clone().negative(),
                 " ".repeat(namelen + 2 - cmp::min(self.name.len() + 3, namelen + 1)),
                 "Start Date".underlined(),
-                self.start_date.clone().italic(),
-                " ".repeat(cmp::max(cmp::max(namelen, 32) - 32, 10)),
+                start_date.clone().italic(),
+                pad(cmp::max(cmp::max(namelen, 32) - 32, 10), &start_date, 17),
+                self.details(cmp::max(cmp::max(namelen, 32) - 32, 10)),
                 "Total Time".underlined(),
-                self.total_time.clone().italic(),
+                self.total_time.to_string().italic(),
                 " ".repeat(cmp::max(cmp::max(namelen, 23) - 23, 10))
             );
 
             let binding = self.order_hours_per_day().unwrap().clone();
             for (i, proj) in binding.iter().enumerate() {
+                let day = display_day(proj.0, date_format);
                 if i == binding.len() - 1 {
                     println!(
                         "    ╰─{}: {}{}│",
-                        proj.0,
-                        proj.1.clone().italic(),
-                        " ".repeat(cmp::max(cmp::max(namelen, 23) - 23, 10))
+                        day,
+                        proj.1.to_string().italic(),
+                        pad(cmp::max(cmp::max(namelen, 23) - 23, 10), &day, 8)
                     );
                 } else {
                     println!(
                         "    ├─{}: {}{}│",
-                        proj.0,
-                        proj.1.clone().italic(),
-                        " ".repeat(cmp::max(cmp::max(namelen, 23) - 23, 10))
+                        day,
+                        proj.1.to_string().italic(),
+                        pad(cmp::max(cmp::max(namelen, 23) - 23, 10), &day, 8)
                     );
                 }
             }
         }
     }
 
-    fn order_hours_per_day(&mut self) -> Result<Vec<(&String, &String)>, ParseError> {
+    fn order_hours_per_day(&mut self) -> Result<Vec<(&String, &TrackedTime)>, ParseError> {
         let mut sorted_hours: Vec<(_, _)> = self.hours_per_day.iter().collect::<Vec<(_, _)>>();
 
         sorted_hours.sort_by(|day1, day2| {
-            NaiveDate::parse_from_str(day1.0, "%m/%d/%y")
+            NaiveDate::parse_from_str(day1.0, DAY_KEY_FORMAT)
                 .unwrap()
-                .cmp(&NaiveDate::parse_from_str(day2.0, "%m/%d/%y").unwrap())
+                .cmp(&NaiveDate::parse_from_str(day2.0, DAY_KEY_FORMAT).unwrap())
         });
 
         // Create a new HashMap from the sorted Vec
@@ -209,59 +438,403 @@ impl Project {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Pause {
+    pub start: DateTime<Local>,
+    pub end: Option<DateTime<Local>>,
+}
+
+/// Time between `start` and `end` that isn't covered by any of `pauses`.
+fn active_time(start: DateTime<Local>, end: DateTime<Local>, pauses: &[Pause]) -> TimeDelta {
+    let paused = pauses
+        .iter()
+        .filter(|pause| pause.start < end)
+        .map(|pause| cmp::min(pause.end.unwrap_or(end), end) - cmp::max(pause.start, start))
+        .filter(|pause| *pause > TimeDelta::zero())
+        .fold(TimeDelta::zero(), |acc, pause| acc + pause);
+    cmp::max(end - start - paused, TimeDelta::zero())
+}
+
+/// A single recorded stretch of work on a project.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimeEntry {
+    pub project: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    #[serde(default)]
+    pub pauses: Vec<Pause>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl TimeEntry {
+    pub fn duration(&self) -> TimeDelta {
+        active_time(self.start, self.end, &self.pauses)
+    }
+
+    /// Splits the entry's active time across the local calendar days it touches. Days are
+    /// bounded by actual local midnights, so DST transitions give 23 or 25 hour days.
+    pub fn time_per_day(&self) -> Vec<(NaiveDate, TimeDelta)> {
+        let mut days = Vec::new();
+        let mut date = self.start.date_naive();
+        let mut day_start = self.start;
+        while day_start < self.end || days.is_empty() {
+            let next_date = date.succ_opt().expect("date out of range");
+            let day_end = cmp::min(local_midnight(next_date), self.end);
+            days.push((date, active_time(day_start, day_end, &self.pauses)));
+            date = next_date;
+            day_start = day_end;
+        }
+        days
+    }
+}
+
+/// Start of `date` in local time. Where a DST change skips midnight, the day starts at the
+/// first instant that exists.
+fn local_midnight(date: NaiveDate) -> DateTime<Local> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    (0..=3)
+        .find_map(|hours| {
+            (midnight + TimeDelta::hours(hours))
+                .and_local_timezone(Local)
+                .earliest()
+        })
+        .expect("no valid local time at the start of the day")
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Session {
-    pub elapsed: u64,
-    pub paused: bool,
+    pub project: String,
+    pub start: DateTime<Local>,
+    pub pauses: Vec<Pause>,
+    /// Last time a `start` loop owning this session wrote it to disk. Detached sessions have
+    /// none, since nothing is expected to keep them alive.
+    #[serde(default)]
+    pub checkpoint: Option<DateTime<Local>>,
+    /// What is being worked on, recorded with the entry when the session stops.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
 }
 
 impl Session {
-    pub fn new() -> Self {
+    pub fn new(project: &str) -> Self {
         Session {
-            elapsed: 0,
-            paused: false,
+            project: project.to_string(),
+            start: Local::now(),
+            pauses: Vec::new(),
+            checkpoint: None,
+            note: None,
+        }
+    }
+
+    /// Whether the `start` loop that owned this session stopped checkpointing it without
+    /// recording it, e.g. because it crashed or its terminal was closed.
+    pub fn is_orphaned(&self) -> bool {
+        self.checkpoint
+            .is_some_and(|checkpoint| Local::now() - checkpoint > ORPHAN_AFTER)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pauses.last().is_some_and(|pause| pause.end.is_none())
+    }
+
+    pub fn pause(&mut self, at: DateTime<Local>) {
+        if !self.is_paused() {
+            self.pauses.push(Pause {
+                start: at,
+                end: None,
+            });
         }
     }
 
+    pub fn resume(&mut self, at: DateTime<Local>) {
+        if let Some(pause) = self.pauses.last_mut() {
+            if pause.end.is_none() {
+                pause.end = Some(at);
+            }
+        }
+    }
+
+    /// Time spent running between the session start and `at`, excluding pauses.
+    pub fn elapsed_at(&self, at: DateTime<Local>) -> TimeDelta {
+        active_time(self.start, at, &self.pauses)
+    }
+
+    pub fn elapsed(&self) -> TimeDelta {
+        self.elapsed_at(Local::now())
+    }
+
     pub fn format_elapsed(&self) -> String {
+        let elapsed = TrackedTime::from(self.elapsed());
+        if self.start.date_naive() == Local::now().date_naive() {
+            return elapsed.to_string();
+        }
         format!(
-            "{:02}:{:02}:{:02}",
-            self.elapsed / 3600,
-            (self.elapsed / 60) % 60,
-            self.elapsed % 60
+            "{} (today: {})",
+            elapsed,
+            TrackedTime::from(self.elapsed_today())
         )
     }
+
+    /// Part of the elapsed time that falls on the current local day, for sessions that have
+    /// been running since before midnight.
+    pub fn elapsed_today(&self) -> TimeDelta {
+        self.to_entry(Local::now())
+            .time_per_day()
+            .last()
+            .map(|(_, time)| *time)
+            .unwrap_or_default()
+    }
+
+    /// Closes the session at `end`, turning it into a recorded entry.
+    pub fn to_entry(&self, end: DateTime<Local>) -> TimeEntry {
+        let mut pauses = self.pauses.clone();
+        if let Some(pause) = pauses.last_mut() {
+            if pause.end.is_none() {
+                pause.end = Some(end);
+            }
+        }
+        TimeEntry {
+            project: self.project.clone(),
+            start: self.start,
+            end,
+            pauses,
+            note: self.note.clone(),
+        }
+    }
 }
 
-impl Default for Session {
-    fn default() -> Self {
-        Self::new()
+/// How often a `start` loop writes its running session to disk.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a checkpointed session can go without a new checkpoint before it is considered
+/// abandoned by its `start` loop.
+const ORPHAN_AFTER: TimeDelta = TimeDelta::seconds(90);
+
+/// Version of the on-disk format written by `save_to_file`. Files without a version are the
+/// original bare map of projects (version 1).
+const DATA_VERSION: u32 = 3;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct DataFile {
+    version: u32,
+    projects: HashMap<String, Project>,
+    entries: Vec<TimeEntry>,
+    #[serde(default)]
+    current_session: Option<Session>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    clients: HashMap<String, Client>,
+}
+
+impl DataFile {
+    /// Brings a file of any older version up to `DATA_VERSION`.
+    fn migrate(value: serde_json::Value) -> serde_json::Result<Self> {
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .unwrap_or(1);
+        if version > DATA_VERSION.into() {
+            return Err(serde::de::Error::custom(format!(
+                "data file version {} is newer than this program supports ({})",
+                version, DATA_VERSION
+            )));
+        }
+        let mut data = match version {
+            1 => DataFile::migrate_v1(serde_json::from_value(value)?),
+            _ => serde_json::from_value(value)?,
+        };
+        if data.version < 3 {
+            data.migrate_v2();
+        }
+        Ok(data)
+    }
+
+    /// Turns the per-day totals of a version 1 file into one synthetic entry per project and
+    /// day, starting at midnight, so that nothing is lost when totals become derived.
+    fn migrate_v1(projects: HashMap<String, Project>) -> Self {
+        let mut entries = Vec::new();
+        for (key, project) in projects.iter() {
+            for (day, time) in project.hours_per_day.iter() {
+                let Ok(date) = NaiveDate::parse_from_str(day, LEGACY_DAY_FORMAT) else {
+                    continue;
+                };
+                let Some(start) = date
+                    .and_time(NaiveTime::MIN)
+                    .and_local_timezone(Local)
+                    .earliest()
+                else {
+                    continue;
+                };
+                let Some(end) = time
+                    .to_delta()
+                    .and_then(|time| start.checked_add_signed(time))
+                else {
+                    continue;
+                };
+                entries.push(TimeEntry {
+                    project: key.clone(),
+                    start,
+                    end,
+                    pauses: Vec::new(),
+                    note: Some(String::from("Migrated from daily total")),
+                });
+            }
+        }
+        entries.sort_by_key(|entry| entry.start);
+
+        DataFile {
+            version: 2,
+            projects,
+            entries,
+            current_session: None,
+            clients: HashMap::new(),
+        }
+    }
+
+    /// Rewrites the US-style "mm/dd/yy" days and start dates of a version 2 file as ISO 8601.
+    fn migrate_v2(&mut self) {
+        for project in self.projects.values_mut() {
+            if let Ok(start_date) =
+                NaiveDateTime::parse_from_str(&project.start_date, LEGACY_START_DATE_FORMAT)
+            {
+                project.start_date = start_date.format(START_DATE_FORMAT).to_string();
+            }
+            project.hours_per_day = project
+                .hours_per_day
+                .drain()
+                .map(|(day, time)| {
+                    let day = NaiveDate::parse_from_str(&day, LEGACY_DAY_FORMAT)
+                        .map(|date| date.format(DAY_KEY_FORMAT).to_string())
+                        .unwrap_or(day);
+                    (day, time)
+                })
+                .collect();
+        }
+        self.version = 3;
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TimeTracking {
-    pub today: String,
     pub projects: HashMap<String, Project>,
-    pub current_project: Option<Project>,
+    pub entries: Vec<TimeEntry>,
     pub current_session: Option<Session>,
+    /// Billing defaults for the projects of each client, by client name.
+    pub clients: HashMap<String, Client>,
+    /// Whether the `start` loop owns the terminal, in which case messages are queued in
+    /// `messages` for its dashboard instead of being printed.
+    #[serde(skip)]
+    pub interactive: bool,
+    #[serde(skip)]
+    pub messages: RefCell<Vec<(String, Color)>>,
+    /// How query commands print their results.
+    #[serde(skip)]
+    pub output: Output,
+    #[serde(skip)]
+    pub config: Config,
+    #[serde(skip)]
+    pub data_file: PathBuf,
+    #[serde(skip)]
+    loaded_at: Option<SystemTime>,
 }
 
 impl TimeTracking {
-    pub fn new() -> Self {
-        return TimeTracking {
-            today: Local::now().format("%m/%d/%y").to_string(),
-            projects: TimeTracking::load_from_file().unwrap_or_default(),
-            current_project: None,
-            current_session: None,
+    /// Loads the data file, starting out empty if there is none yet. A file that exists but
+    /// can't be read is an error rather than an empty store, so it never gets overwritten.
+    pub fn new(data_file: PathBuf, config: Config) -> io::Result<Self> {
+        let data = match TimeTracking::load_from_file(&data_file) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => DataFile::default(),
+            data => data?,
+        };
+        let mut tt = TimeTracking {
+            projects: data.projects,
+            entries: data.entries,
+            current_session: data.current_session,
+            clients: data.clients,
+            interactive: false,
+            messages: RefCell::default(),
+            output: Output::Table,
+            loaded_at: TimeTracking::modified_at(&data_file),
+            config,
+            data_file,
         };
+        tt.recompute_totals();
+        Ok(tt)
+    }
+
+    /// Picks up changes made to the data file by other invocations since it was last read or
+    /// written, so a `start` loop reflects `pause`, `resume` and `stop` run from another shell.
+    pub fn reload_if_changed(&mut self) -> io::Result<()> {
+        let modified = TimeTracking::modified_at(&self.data_file);
+        if modified == self.loaded_at {
+            return Ok(());
+        }
+        let data = TimeTracking::load_from_file(&self.data_file)?;
+        self.projects = data.projects;
+        self.entries = data.entries;
+        self.current_session = data.current_session;
+        self.clients = data.clients;
+        self.loaded_at = modified;
+        self.recompute_totals();
+        Ok(())
+    }
+
+    /// Takes the advisory lock on the data file and reloads it if another instance changed it,
+    /// so that whatever the caller modifies next is applied on top of the latest data. The lock
+    /// is released when the returned file is dropped.
+    fn lock(&mut self) -> io::Result<File> {
+        if let Some(dir) = self
+            .data_file
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+        {
+            fs::create_dir_all(dir)?;
+        }
+        let lock = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.sibling_file("lock"))?;
+        lock.lock()?;
+        self.reload_if_changed()?;
+        Ok(lock)
+    }
+
+    fn info(&self, info: &str, c: Color) {
+        if self.interactive {
+            self.messages
+                .borrow_mut()
+                .push((info.trim_end().to_string(), c));
+        } else {
+            println!("{}", info.trim_end().with(c));
+        }
+    }
+
+    /// Rebuilds every project's `hours_per_day` and `total_time` from the recorded entries.
+    /// Entries refer to projects by their key in `projects`, which is what sessions are
+    /// started with, so a hand-edited file with a different `name` inside a project gets that
+    /// name replaced by the key, and the project is shown and looked up by the same name.
+    pub fn recompute_totals(&mut self) {
+        for (key, project) in self.projects.iter_mut() {
+            project.name.clone_from(key);
+            project.hours_per_day.clear();
+            for entry in self.entries.iter().filter(|entry| entry.project == *key) {
+                for (date, time) in entry.time_per_day() {
+                    *project
+                        .hours_per_day
+                        .entry(date.format(DAY_KEY_FORMAT).to_string())
+                        .or_default() += time.into();
+                }
+            }
+            project.total_time = project.hours_per_day.values().sum();
+        }
     }
 
     pub fn sort_projects(&mut self) -> Vec<Project> {
         let mut sorted_projects: Vec<_> = self.projects.clone().into_values().collect();
 
         sorted_projects.sort_by_key(|project| {
-            NaiveDateTime::parse_from_str(&project.start_date, "%m/%d/%y %H:%M:%S").unwrap()
+            NaiveDateTime::parse_from_str(&project.start_date, START_DATE_FORMAT).unwrap()
         });
 
         // Create a new HashMap with sorted entries
@@ -273,164 +846,473 @@ impl TimeTracking {
         sorted_projects
     }
 
-    pub fn start_project(&mut self, project_name: &str) {
-        print_info(&format!("\u{2714} Starting project: {:?}. Press [a] to stop and quit, [s] to switch projects or [p] to pause.\n", project_name), false, Color::Green);
-        let session = Session::new();
+    /// Starts a session for `project_name`, creating the project if needed. Returns `false`
+    /// if another instance started tracking something in the meantime.
+    pub fn start_project(&mut self, project_name: &str, note: Option<&str>) -> bool {
+        let _lock = self.lock().expect("unable to lock data file");
+        if let Some(ref session) = self.current_session {
+            self.info(
+                &format!(
+                    "\u{26a0} Project {:?} is already being tracked. Stop it first with `stop`.",
+                    session.project
+                ),
+                self.config.colors.warning(),
+            );
+            return false;
+        }
+        if self.interactive {
+            self.info(
+                &format!("\u{2714} Starting project: {:?}.", project_name),
+                self.config.colors.success(),
+            );
+        } else {
+            self.info(
+                &format!("\u{2714} Started tracking project {:?} in the background. Use `status`, `pause`, `resume` or `stop` to control it.", project_name),
+                self.config.colors.success(),
+            );
+        }
+        let mut session = Session::new(project_name);
+        session.note = note.map(str::to_string);
         self.current_session = Some(session);
 
-        if let Some(project) = self.projects.get_mut(project_name) {
-            if project.hours_per_day.get_mut(&self.today).is_none() {
-                project
-                    .hours_per_day
-                    .insert(self.today.clone(), "00:00:00".to_string());
-            }
-
-            self.current_project = Some(project.clone());
-        } else {
-            let mut new_project = Project::new(
+        if !self.projects.contains_key(project_name) {
+            let new_project = Project::new(
                 project_name,
-                &Local::now().format("%m/%d/%y %H:%M:%S").to_string(),
+                &Local::now().format(START_DATE_FORMAT).to_string(),
             );
-            new_project
-                .hours_per_day
-                .insert(self.today.clone(), "00:00:00".to_string());
-            self.projects
-                .insert(project_name.to_owned(), new_project.clone());
-            self.current_project = Some(new_project);
+            self.projects.insert(project_name.to_owned(), new_project);
         }
+        self.save_to_file().expect("unable to save to file");
+        true
     }
 
     pub fn pause(&mut self) {
+        let _lock = self.lock().expect("unable to lock data file");
         if let Some(ref mut session) = self.current_session {
-            if !session.paused {
-                session.paused = true;
-                print_info("\u{1f6c8} Paused. Press [r] to resume.", true, Color::Blue);
+            if !session.is_paused() {
+                session.pause(Local::now());
+                if self.interactive {
+                    self.info(
+                        &format!(
+                            "\u{1f6c8} Paused. Press [{}] to resume.",
+                            self.config.keys.resume
+                        ),
+                        self.config.colors.info(),
+                    );
+                } else {
+                    self.info(
+                        "\u{1f6c8} Paused. Use `resume` to continue.",
+                        self.config.colors.info(),
+                    );
+                }
+                self.save_to_file().expect("unable to save to file");
             } else {
-                print_info("\u{26a0} Project is already paused.", true, Color::Red);
+                self.info(
+                    "\u{26a0} Project is already paused.",
+                    self.config.colors.warning(),
+                );
             }
+        } else {
+            self.info(
+                "\u{26a0} No project is being tracked.",
+                self.config.colors.warning(),
+            );
         }
     }
 
     pub fn resume(&mut self) {
+        let _lock = self.lock().expect("unable to lock data file");
         if let Some(ref mut session) = self.current_session {
-            if session.paused {
-                print_info(&format!("\u{1f6c8} Resumed project: {:?}. Press [a] to stop and quit, [s] to switch projects or [p] to pause.", self.current_project.clone().unwrap().name), false, Color::Blue);
-                session.paused = false;
+            if session.is_paused() {
+                session.resume(Local::now());
+                let project = session.project.clone();
+                self.info(
+                    &format!("\u{1f6c8} Resumed project: {:?}.", project),
+                    self.config.colors.info(),
+                );
+                self.save_to_file().expect("unable to save to file");
             } else {
-                print_info("\u{26a0} Project is already running.", false, Color::Red);
+                self.info(
+                    "\u{26a0} Project is already running.",
+                    self.config.colors.warning(),
+                );
             }
+        } else {
+            self.info(
+                "\u{26a0} No project is being tracked.",
+                self.config.colors.warning(),
+            );
         }
     }
 
     pub fn switch_project(&mut self, project_name: &str) {
         self.stop_project();
-        self.start_project(project_name);
+        self.start_project(project_name, None);
+    }
+
+    /// Attaches `note` to the running session, or removes its note. Returns `false` if no
+    /// project is being tracked.
+    pub fn set_note(&mut self, note: Option<String>) -> bool {
+        let _lock = self.lock().expect("unable to lock data file");
+        let Some(ref mut session) = self.current_session else {
+            self.info(
+                "\u{26a0} No project is being tracked.",
+                self.config.colors.warning(),
+            );
+            return false;
+        };
+        session.note = note;
+        let message = match session.note {
+            Some(ref note) => format!(
+                "\u{2714} Noted {:?} for project {:?}.",
+                note, session.project
+            ),
+            None => format!(
+                "\u{2714} Removed the note for project {:?}.",
+                session.project
+            ),
+        };
+        self.save_to_file().expect("unable to save to file");
+        self.info(&message, self.config.colors.success());
+        true
+    }
+
+    /// Warnings for the budgets the running session has pushed its project to 80% or 100% of,
+    /// each with a key that stays the same while the same threshold is reached, so callers
+    /// can show every warning once.
+    pub fn budget_alerts(&self) -> Vec<(String, String)> {
+        let Some(ref session) = self.current_session else {
+            return Vec::new();
+        };
+        let Some(project) = self.projects.get(&session.project) else {
+            return Vec::new();
+        };
+        let unrecorded = session.to_entry(Local::now()).time_per_day();
+        project
+            .budget_usage(&unrecorded)
+            .into_iter()
+            .filter_map(|usage| {
+                let threshold = [100, 80]
+                    .into_iter()
+                    .find(|threshold| usage.percent() >= *threshold)?;
+                let label = usage.label.to_lowercase();
+                let message = if threshold == 100 {
+                    format!(
+                        "\u{26a0} Project {:?} is over its {}: {} of {}.",
+                        session.project, label, usage.used, usage.budget
+                    )
+                } else {
+                    format!(
+                        "\u{26a0} Project {:?} has used {}% of its {}: {} of {}.",
+                        session.project,
+                        usage.percent(),
+                        label,
+                        usage.used,
+                        usage.budget
+                    )
+                };
+                Some((
+                    format!("{}/{}/{}", session.project, label, threshold),
+                    message,
+                ))
+            })
+            .collect()
     }
 
     pub fn stop_project(&mut self) {
+        let _lock = self.lock().expect("unable to lock data file");
+        if let Some(session) = self.current_session.take() {
+            self.entries.push(session.to_entry(Local::now()));
+            self.recompute_totals();
+            self.save_to_file().expect("unable to save to file");
+        }
+    }
+
+    /// Records that the `start` loop owning the running session is still alive.
+    pub fn checkpoint(&mut self) {
+        let _lock = self.lock().expect("unable to lock data file");
         if let Some(ref mut session) = self.current_session {
-            if !session.paused {
-                session.paused = true;
-            }
+            session.checkpoint = Some(Local::now());
+            self.save_to_file().expect("unable to save to file");
+        }
+    }
+
+    /// Hands the running session over to background tracking, as if started with `--detach`.
+    pub fn detach(&mut self) {
+        let _lock = self.lock().expect("unable to lock data file");
+        if let Some(ref mut session) = self.current_session {
+            session.checkpoint = None;
+            self.save_to_file().expect("unable to save to file");
+        }
+    }
+
+    /// Asks what to do with a session left behind by a `start` loop that never stopped it:
+    /// keep tracking it, record it up to its last checkpoint, or throw it away.
+    pub fn recover_orphaned_session(&mut self) {
+        let Some(session) = self.current_session.clone() else {
+            return;
+        };
+        let Some(checkpoint) = session.checkpoint.filter(|_| session.is_orphaned()) else {
+            return;
+        };
+        let found = format!(
+            "\u{26a0} Found an unfinished session for project {:?}, last saved at {} with {} tracked.",
+            session.project,
+            checkpoint.format(&format!("{} %H:%M:%S", self.config.date_format)),
+            TrackedTime::from(session.elapsed_at(checkpoint))
+        );
+        // With either end redirected the question would go unseen or unanswered, and the notice
+        // must not end up in the output.
+        if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+            eprintln!("{}", found);
+            eprintln!(
+                "\u{1f6c8} Run any command from a terminal to recover, close out or discard it."
+            );
+            return;
+        }
+        println!("{}", found.with(self.config.colors.warning()));
 
-            let elapsed_time_str = session.format_elapsed();
+        loop {
+            print!(
+                "[r]ecover and keep tracking, [c]lose out at {}, or [d]iscard? ",
+                checkpoint.format("%H:%M:%S")
+            );
+            io::stdout().flush().expect("Failed to flush stdout");
+            let mut input = String::new();
+            if io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+                return;
+            }
+            if !["r", "c", "d"].contains(&input.trim()) {
+                continue;
+            }
 
-            if let Some(ref mut project) = self
-                .projects
-                .get_mut(&self.current_project.clone().unwrap().name)
+            let _lock = self.lock().expect("unable to lock data file");
+            if self
+                .current_session
+                .as_ref()
+                .is_none_or(|current| current.start != session.start)
             {
-                let mut carry_seconds = 0;
-                let mut carry_minutes = 0;
-                project.total_time = project
-                    .total_time
-                    .split(':')
-                    .rev()
-                    .zip(elapsed_time_str.split(':').rev())
-                    .map(|(a, b)| {
-                        let a_val = a.parse::<i64>().unwrap_or(0);
-                        let b_val = b.parse::<i64>().unwrap_or(0);
-                        let mut sum = a_val + b_val + carry_seconds;
-
-                        carry_seconds = sum / 60;
-                        sum %= 60;
-
-                        sum += carry_minutes;
-                        carry_minutes = sum / 60;
-                        sum %= 60;
-
-                        format!("{:02}", sum)
-                    })
-                    .collect::<Vec<_>>()
-                    .into_iter()
-                    .rev()
-                    .collect::<Vec<_>>()
-                    .join(":");
-
-                if let Some(entry) = project.hours_per_day.get_mut(&self.today) {
-                    let mut carry_seconds = 0;
-                    let mut carry_minutes = 0;
-                    let updated_time = entry
-                        .split(':')
-                        .rev()
-                        .zip(elapsed_time_str.split(':').rev())
-                        .map(|(a, b)| {
-                            let a_val = a.parse::<i64>().unwrap_or(0);
-                            let b_val = b.parse::<i64>().unwrap_or(0);
-                            let mut sum = a_val + b_val + carry_seconds;
-
-                            carry_seconds = sum / 60;
-                            sum %= 60;
-
-                            sum += carry_minutes;
-                            carry_minutes = sum / 60;
-                            sum %= 60;
-
-                            format!("{:02}", sum)
-                        })
-                        .collect::<Vec<_>>()
-                        .into_iter()
-                        .rev()
-                        .collect::<Vec<_>>()
-                        .join(":");
-                    project
-                        .hours_per_day
-                        .insert(self.today.to_owned(), updated_time);
+                println!(
+                    "{}",
+                    "\u{1f6c8} The session was already handled by another instance."
+                        .with(self.config.colors.info())
+                );
+                return;
+            }
+            match input.trim() {
+                "r" => {
+                    let mut session = session;
+                    // The time the session spent unattended is not counted.
+                    if !session.is_paused() {
+                        session.pause(checkpoint);
+                        session.resume(Local::now());
+                    }
+                    session.checkpoint = None;
+                    self.current_session = Some(session.clone());
+                    self.save_to_file().expect("unable to save to file");
+                    println!(
+                        "{}",
+                        format!(
+                            "\u{2714} Recovered project {:?}. It keeps tracking in the background; use `start {}` to attach to it.",
+                            session.project, session.project
+                        )
+                        .with(self.config.colors.success())
+                    );
+                    return;
+                }
+                "c" => {
+                    self.current_session = None;
+                    self.entries.push(session.to_entry(checkpoint));
+                    self.recompute_totals();
+                    self.save_to_file().expect("unable to save to file");
+                    println!(
+                        "{}",
+                        format!(
+                            "\u{2714} Recorded {} for project {:?}.",
+                            TrackedTime::from(session.elapsed_at(checkpoint)),
+                            session.project
+                        )
+                        .with(self.config.colors.success())
+                    );
+                    return;
+                }
+                "d" => {
+                    self.current_session = None;
+                    self.save_to_file().expect("unable to save to file");
+                    println!(
+                        "{}",
+                        "\u{2714} Discarded the unfinished session."
+                            .with(self.config.colors.success())
+                    );
+                    return;
                 }
+                _ => {}
             }
+        }
+    }
 
-            self.current_project = None;
-            self.current_session = None;
-            self.save_to_file().expect("unable to save to file");
+    /// Prints the running session, if any.
+    pub fn status(&self) {
+        match self.output {
+            Output::Json => {
+                let session = self.current_session.as_ref();
+                print_json(&StatusJson {
+                    state: match session {
+                        Some(session) if session.is_paused() => "paused",
+                        Some(_) => "running",
+                        None => "idle",
+                    },
+                    project: session.map(|session| session.project.as_str()),
+                    started: session.map(|session| session.start),
+                    elapsed_seconds: session.map(|session| session.elapsed().num_seconds()),
+                    today_seconds: session.map(|session| session.elapsed_today().num_seconds()),
+                    note: session.and_then(|session| session.note.as_deref()),
+                });
+                return;
+            }
+            Output::Plain => {
+                match self.current_session {
+                    Some(ref session) => print_plain(&[
+                        session.project.clone(),
+                        String::from(if session.is_paused() {
+                            "paused"
+                        } else {
+                            "running"
+                        }),
+                        TrackedTime::from(session.elapsed()).to_string(),
+                        session.note.clone().unwrap_or_default(),
+                    ]),
+                    None => print_plain(&["idle"]),
+                }
+                return;
+            }
+            Output::Table => {}
+        }
+        match self.current_session {
+            Some(ref session) => {
+                println!(
+                    "\u{23f1} Project: {} \u{2016} Elapsed time: {} \u{2016} {} \u{2016} Started at {}",
+                    session.project,
+                    session.format_elapsed(),
+                    if session.is_paused() {
+                        "Paused"
+                    } else {
+                        "Running"
+                    },
+                    session
+                        .start
+                        .format(&format!("{} %H:%M:%S", self.config.date_format))
+                );
+                if let Some(ref note) = session.note {
+                    println!("\u{1f5c9} {}", note);
+                }
+            }
+            None => println!(
+                "{}",
+                "\u{1f6c8} No project is being tracked.".with(self.config.colors.info())
+            ),
         }
     }
 
-    pub fn list_project_or_all(&mut self, project_name: Option<&str>) {
+    /// Displays one project, or every project matching `filter` that isn't archived (all of
+    /// them with `include_archived`, archived ones marked as such).
+    pub fn list_project_or_all(
+        &mut self,
+        project_name: Option<&str>,
+        include_archived: bool,
+        filter: &ProjectFilter,
+    ) {
         match project_name {
-            Some(name) => {
-                let sorted_projects = self.sort_projects();
-                let project = sorted_projects.iter().find(|proj| proj.name == name);
-                match project {
-                    Some(project) => {
-                        println!(
-                            "{}",
-                            format!(
-                                "\u{1f6c8} Displaying tracking information for project: {:?}",
-                                name
-                            )
-                            .blue()
-                        );
-                        project.clone().display(false, true, project.name.len() + 2);
+            Some(name) => match self.projects.get(name) {
+                Some(project) if self.output == Output::Json => {
+                    print_json(&ProjectDetailsJson {
+                        project: ProjectJson::new(project),
+                        entries: self
+                            .entries_of(name)
+                            .map(|entry| EntryJson::new(entry, &project.name))
+                            .collect(),
+                    });
+                }
+                Some(project) if self.output == Output::Plain => {
+                    let mut days: Vec<_> = project.hours_per_day.iter().collect();
+                    days.sort();
+                    for (day, time) in days {
+                        print_plain(&[day.clone(), time.to_string()]);
                     }
-                    None => {
-                        println!(
-                            "{}",
-                            format!("\u{26a0} Project {:?} not found!", name).red()
-                        );
+                }
+                Some(project) => {
+                    println!(
+                        "{}",
+                        format!(
+                            "\u{1f6c8} Displaying tracking information for project: {:?}",
+                            name
+                        )
+                        .with(self.config.colors.info())
+                    );
+                    project.clone().display(
+                        false,
+                        true,
+                        project.name.len() + 2,
+                        &self.config.date_format,
+                    );
+                    let mut per_day: BTreeMap<NaiveDate, Vec<&TimeEntry>> = BTreeMap::new();
+                    for entry in self.entries_of(name) {
+                        for (date, _) in entry.time_per_day() {
+                            per_day.entry(date).or_default().push(entry);
+                        }
                     }
+                    self.print_notes(
+                        per_day
+                            .into_iter()
+                            .map(|(date, entries)| {
+                                (date.format(&self.config.date_format).to_string(), entries)
+                            })
+                            .collect(),
+                    );
                 }
-            }
+                None => {
+                    self.warn(&format!("\u{26a0} Project {:?} not found!", name));
+                }
+            },
             None => {
-                let sorted_projects = self.sort_projects();
+                let sorted_projects: Vec<_> = self
+                    .sort_projects()
+                    .into_iter()
+                    .filter(|project| {
+                        (include_archived || !project.archived) && filter.matches(project)
+                    })
+                    .collect();
+                match self.output {
+                    Output::Json => {
+                        print_json(&ProjectListJson {
+                            projects: sorted_projects.iter().map(ProjectJson::new).collect(),
+                        });
+                        return;
+                    }
+                    Output::Plain => {
+                        for project in sorted_projects {
+                            print_plain(&[project.name, project.total_time.to_string()]);
+                        }
+                        return;
+                    }
+                    Output::Table => {}
+                }
+                let sorted_projects: Vec<_> = sorted_projects
+                    .into_iter()
+                    .map(|mut project| {
+                        if project.archived {
+                            project.name.push_str(" (archived)");
+                        }
+                        project
+                    })
+                    .collect();
+                if sorted_projects.is_empty() {
+                    println!(
+                        "{}",
+                        "\u{26a0} No projects to display!".with(self.config.colors.warning())
+                    );
+                    return;
+                }
                 let namelen = sorted_projects.iter().fold(0, |l, proj| {
                     if proj.name.len() > l {
                         proj.name.len()
@@ -442,28 +1324,97 @@ impl TimeTracking {
                 for (i, project) in projects {
                     if i == sorted_projects.len() - 1 {
                         println!("╭{}┤", "─".repeat(cmp::max(namelen + 2, 33)));
-                        project.clone().display(true, false, namelen + 2);
+                        project
+                            .clone()
+                            .display(true, false, namelen + 2, &self.config.date_format);
                     } else if i == 0 {
                         println!("╭{}╮", "─".repeat(cmp::max(namelen + 2, 33)));
-                        project.clone().display(false, false, namelen + 2)
+                        project.clone().display(
+                            false,
+                            false,
+                            namelen + 2,
+                            &self.config.date_format,
+                        );
                     } else {
                         println!("╭{}┤", "─".repeat(cmp::max(namelen + 2, 33)));
-                        project.clone().display(false, false, namelen + 2);
+                        project.clone().display(
+                            false,
+                            false,
+                            namelen + 2,
+                            &self.config.date_format,
+                        );
                     }
                 }
             }
         }
     }
 
-    pub fn get_day_info(&mut self, day: &String) {
+    pub fn get_day_info(&mut self, day: &str, filter: &ProjectFilter) {
+        let date = match dates::day_arg(day, &self.config.date_format, Local::now().date_naive()) {
+            Ok(date) => date,
+            Err(e) => {
+                self.warn(&e);
+                return;
+            }
+        };
+        let key = date.format(DAY_KEY_FORMAT).to_string();
+        let day = &date.format(&self.config.date_format).to_string();
         let hours = self
             .sort_projects()
             .into_iter()
-            .filter(|proj| proj.hours_per_day.contains_key(day))
-            .map(|proj| (proj.name, proj.hours_per_day.get(day).unwrap().clone()))
-            .collect::<Vec<(String, String)>>();
+            .filter(|proj| filter.matches(proj) && proj.hours_per_day.contains_key(&key))
+            .map(|proj| (proj.name, *proj.hours_per_day.get(&key).unwrap()))
+            .collect::<Vec<(String, TrackedTime)>>();
+        let day_total_time: TrackedTime = hours.iter().map(|(_, time)| time).sum();
+        let entries: Vec<(String, Vec<&TimeEntry>)> = hours
+            .iter()
+            .map(|(name, _)| {
+                let entries = self
+                    .entries_of(name)
+                    .filter(|entry| {
+                        entry
+                            .time_per_day()
+                            .iter()
+                            .any(|(entry_date, _)| *entry_date == date)
+                    })
+                    .collect();
+                (name.clone(), entries)
+            })
+            .collect();
+        match self.output {
+            Output::Json => {
+                print_json(&DayJson {
+                    date: key,
+                    total_seconds: day_total_time.seconds(),
+                    projects: hours
+                        .iter()
+                        .map(|(name, time)| TotalJson {
+                            name: name.clone(),
+                            seconds: time.seconds(),
+                        })
+                        .collect(),
+                    entries: entries
+                        .iter()
+                        .flat_map(|(name, entries)| {
+                            entries.iter().map(|entry| EntryJson::new(entry, name))
+                        })
+                        .collect(),
+                });
+                return;
+            }
+            Output::Plain => {
+                for (name, time) in hours.iter() {
+                    print_plain(&[name.clone(), time.to_string()]);
+                }
+                return;
+            }
+            Output::Table => {}
+        }
         if hours.is_empty() {
-            println!("{}", format!("\u{26a0} Day {:?} not found!", day).red());
+            println!(
+                "{}",
+                format!("\u{26a0} Day {:?} not found!", day).with(self.config.colors.warning())
+            );
             return;
         }
         println!(
@@ -472,40 +1423,16 @@ impl TimeTracking {
                 "\u{1f6c8} Displaying tracking information for day {:?}",
                 day
             )
-            .blue()
+            .with(self.config.colors.info())
         );
         let namelen = hours.iter().map(|x| x.0.clone().len()).max().unwrap_or(0) + 16;
-        let day_total_time = hours
-            .iter()
-            .map(|a| a.1.split(':'))
-            .collect::<Vec<std::str::Split<'_, char>>>()
-            .into_iter()
-            .map(|b| b.clone().collect::<Vec<&str>>())
-            .collect::<Vec<Vec<&str>>>();
-        let formatted_day_total_time = (0..day_total_time[0].len())
-            .rev()
-            .fold((0, Vec::new()), |(mut carry, mut acc), i| {
-                let sum: i64 = day_total_time
-                    .iter()
-                    .map(|inner| inner[i].trim().parse::<i64>().unwrap_or(0))
-                    .sum();
-                let total = sum + carry;
-                carry = total / 60;
-                acc.insert(0, total % 60);
-                (carry, acc)
-            })
-            .1
-            .iter()
-            .map(|&x| format!("{:02}", x))
-            .collect::<Vec<_>>()
-            .join(":");
         println!("╭{}╮", "─".repeat(cmp::max(namelen, 24)));
         println!(
             "╰─{}{}│\n  ╰─{}: {}{}│",
             day.clone().negative(),
-            " ".repeat(cmp::max(cmp::max(namelen, 9) - 9, 15)),
+            pad(cmp::max(cmp::max(namelen, 9) - 9, 15), day, 8),
             "Total Time".underlined(),
-            formatted_day_total_time.clone().italic(),
+            day_total_time.to_string().italic(),
             " ".repeat(cmp::max(cmp::max(namelen, 23) - 23, 1))
         );
 
@@ -514,7 +1441,7 @@ impl TimeTracking {
                 println!(
                     "    ╰─{}: {}{}│",
                     proj.0,
-                    proj.1.clone().italic(),
+                    proj.1.to_string().italic(),
                     " ".repeat(cmp::max(
                         namelen - 15 - cmp::min(proj.0.len(), namelen - 16),
                         if namelen < 25 { 9 - proj.0.len() } else { 1 }
@@ -524,7 +1451,7 @@ impl TimeTracking {
                 println!(
                     "    ├─{}: {}{}│",
                     proj.0,
-                    proj.1.clone().italic(),
+                    proj.1.to_string().italic(),
                     " ".repeat(cmp::max(
                         namelen - 15 - cmp::min(proj.0.len(), namelen - 16),
                         if namelen < 25 { 9 - proj.0.len() } else { 1 }
@@ -534,50 +1461,608 @@ impl TimeTracking {
         }
 
         println!("─{}╯", "─".repeat(cmp::max(namelen, 24)));
+        self.print_notes(entries);
+    }
+
+    /// Entries recorded for the project `key`, oldest first.
+    fn entries_of<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a TimeEntry> {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.project == key)
+            .collect();
+        entries.sort_by_key(|entry| entry.start);
+        entries.into_iter()
+    }
+
+    /// Draws a box with the notes of `entries`, under the heading each group comes with.
+    /// Draws nothing if none of them has a note.
+    fn print_notes(&self, groups: Vec<(String, Vec<&TimeEntry>)>) {
+        let groups: Vec<_> = groups
+            .into_iter()
+            .map(|(heading, entries)| {
+                let noted: Vec<_> = entries
+                    .into_iter()
+                    .filter(|entry| entry.note.is_some())
+                    .collect();
+                (heading, noted)
+            })
+            .filter(|(_, entries)| !entries.is_empty())
+            .collect();
+        let mut lines = Vec::new();
+        for (i, (heading, entries)) in groups.iter().enumerate() {
+            let last = i == groups.len() - 1;
+            lines.push((
+                String::from(if last { "  ╰─" } else { "  ├─" }),
+                heading.clone(),
+                None,
+            ));
+            for (j, entry) in entries.iter().enumerate() {
+                let glyph = if j == entries.len() - 1 {
+                    "╰─"
+                } else {
+                    "├─"
+                };
+                lines.push((
+                    format!("  {} {}", if last { " " } else { "│" }, glyph),
+                    format!(
+                        "{}-{}",
+                        entry.start.format("%H:%M"),
+                        entry.end.format("%H:%M")
+                    ),
+                    entry.note.clone(),
+                ));
+            }
+        }
+        if !lines.is_empty() {
+            print_box("Notes", &lines);
+        }
+    }
+
+    /// Prints per-project, per-client and per-day totals for the days from `from` to `to`,
+    /// inclusive, of the projects matching `filter`.
+    pub fn report(&mut self, from: NaiveDate, to: NaiveDate, filter: &ProjectFilter) {
+        let in_range = |day: &String| {
+            NaiveDate::parse_from_str(day, DAY_KEY_FORMAT)
+                .is_ok_and(|date| (from..=to).contains(&date))
+        };
+        let mut per_project = Vec::new();
+        let mut per_client: HashMap<String, TrackedTime> = HashMap::new();
+        let mut per_day: HashMap<NaiveDate, TrackedTime> = HashMap::new();
+        for project in self
+            .sort_projects()
+            .into_iter()
+            .filter(|project| filter.matches(project))
+        {
+            let mut project_total = TrackedTime::ZERO;
+            for (day, time) in project
+                .hours_per_day
+                .iter()
+                .filter(|(day, _)| in_range(day))
+            {
+                project_total += *time;
+                *per_day
+                    .entry(NaiveDate::parse_from_str(day, DAY_KEY_FORMAT).unwrap())
+                    .or_default() += *time;
+            }
+            if project_total > TrackedTime::ZERO {
+                if let Some(client) = project.client {
+                    *per_client.entry(client).or_default() += project_total;
+                }
+                per_project.push((project.name, project_total));
+            }
+        }
+        let mut per_client: Vec<_> = per_client.into_iter().collect();
+        per_client.sort();
+        let mut per_day: Vec<_> = per_day.into_iter().collect();
+        per_day.sort();
+        let total: TrackedTime = per_project.iter().map(|(_, time)| time).sum();
+        match self.output {
+            Output::Json => {
+                let totals = |totals: &[(String, TrackedTime)]| {
+                    totals
+                        .iter()
+                        .map(|(name, time)| TotalJson {
+                            name: name.clone(),
+                            seconds: time.seconds(),
+                        })
+                        .collect()
+                };
+                print_json(&ReportJson {
+                    from: from.format(DAY_KEY_FORMAT).to_string(),
+                    to: to.format(DAY_KEY_FORMAT).to_string(),
+                    total_seconds: total.seconds(),
+                    projects: totals(&per_project),
+                    clients: totals(&per_client),
+                    days: per_day
+                        .iter()
+                        .map(|(date, time)| DayTotalJson::new(*date, *time))
+                        .collect(),
+                });
+                return;
+            }
+            Output::Plain => {
+                let rows =
+                    per_project
+                        .iter()
+                        .map(|(name, time)| ("project", name.clone(), time))
+                        .chain(
+                            per_client
+                                .iter()
+                                .map(|(name, time)| ("client", name.clone(), time)),
+                        )
+                        .chain(per_day.iter().map(|(date, time)| {
+                            ("day", date.format(DAY_KEY_FORMAT).to_string(), time)
+                        }))
+                        .chain([("total", String::new(), &total)]);
+                for (kind, name, time) in rows {
+                    print_plain(&[kind, &name, &time.to_string()]);
+                }
+                return;
+            }
+            Output::Table => {}
+        }
+
+        let title = format!(
+            "{} \u{2192} {}",
+            from.format(&self.config.date_format),
+            to.format(&self.config.date_format)
+        );
+        if per_project.is_empty() {
+            println!(
+                "{}",
+                format!("\u{26a0} Nothing was tracked from {}!", title)
+                    .with(self.config.colors.warning())
+            );
+            return;
+        }
+        println!(
+            "{}",
+            format!("\u{1f6c8} Displaying report for {}", title).with(self.config.colors.info())
+        );
+
+        let mut lines = vec![
+            (
+                String::from("  ├─"),
+                String::from("Total Time"),
+                Some(total.to_string()),
+            ),
+            (String::from("  ├─"), String::from("Projects"), None),
+        ];
+        for (i, (name, time)) in per_project.iter().enumerate() {
+            let glyph = if i == per_project.len() - 1 {
+                "╰─"
+            } else {
+                "├─"
+            };
+            lines.push((
+                format!("  │ {}", glyph),
+                name.clone(),
+                Some(time.to_string()),
+            ));
+        }
+        if !per_client.is_empty() {
+            lines.push((String::from("  ├─"), String::from("Clients"), None));
+            for (i, (client, time)) in per_client.iter().enumerate() {
+                let glyph = if i == per_client.len() - 1 {
+                    "╰─"
+                } else {
+                    "├─"
+                };
+                lines.push((
+                    format!("  │ {}", glyph),
+                    client.clone(),
+                    Some(time.to_string()),
+                ));
+            }
+        }
+        lines.push((String::from("  ╰─"), String::from("Days"), None));
+        for (i, (date, time)) in per_day.iter().enumerate() {
+            let glyph = if i == per_day.len() - 1 {
+                "╰─"
+            } else {
+                "├─"
+            };
+            lines.push((
+                format!("    {}", glyph),
+                date.format(&self.config.date_format).to_string(),
+                Some(time.to_string()),
+            ));
+        }
+        print_box(&title, &lines);
     }
 
-    pub fn save_to_file(&self) -> io::Result<()> {
-        let json_data = serde_json::to_string_pretty(&self.projects)
+    /// Rewrites the data file with every project's totals recomputed from its entries,
+    /// reporting the projects whose stored totals were wrong.
+    pub fn repair(&mut self) -> io::Result<()> {
+        let _lock = self.lock()?;
+        let stored = TimeTracking::load_from_file(&self.data_file)?.projects;
+        let mut repaired = 0;
+        let mut projects: Vec<_> = self.projects.iter().collect();
+        projects.sort_by_key(|(key, _)| *key);
+        for (key, project) in projects {
+            let Some(old) = stored.get(key) else {
+                continue;
+            };
+            if old.total_time != project.total_time || old.hours_per_day != project.hours_per_day {
+                repaired += 1;
+                println!(
+                    "{}",
+                    format!(
+                        "\u{2714} Repaired project {:?}: total time {} -> {}",
+                        key, old.total_time, project.total_time
+                    )
+                    .with(self.config.colors.success())
+                );
+            }
+        }
+        if repaired == 0 {
+            println!(
+                "{}",
+                "\u{1f6c8} All project totals are consistent.".with(self.config.colors.info())
+            );
+        }
+        self.save_to_file()
+    }
+
+    /// Writes the data file atomically: the new contents go to a temporary file that then
+    /// replaces the old one, so readers never see a partially written file. Callers hold the
+    /// lock from `lock`.
+    pub fn save_to_file(&mut self) -> io::Result<()> {
+        if TimeTracking::modified_at(&self.data_file) != self.loaded_at {
+            return Err(io::Error::other(
+                "data file was changed by another instance since it was read",
+            ));
+        }
+        let data = DataFile {
+            version: DATA_VERSION,
+            projects: self.projects.clone(),
+            entries: self.entries.clone(),
+            current_session: self.current_session.clone(),
+            clients: self.clients.clone(),
+        };
+        let json_data = serde_json::to_string_pretty(&data)
             .expect("Failed to serialize time tracking data to JSON");
+        let tmp_path = self.sibling_file("tmp");
         let mut file = OpenOptions::new()
             .write(true)
             .create(true)
-            .open("time_tracker_data.json")?;
+            .truncate(true)
+            .open(&tmp_path)?;
         file.write_all(json_data.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.data_file)?;
+        self.loaded_at = TimeTracking::modified_at(&self.data_file);
         Ok(())
     }
 
-    pub fn load_from_file() -> io::Result<HashMap<String, Project>> {
-        let mut file = File::open("time_tracker_data.json")?;
+    /// Path next to the data file with `extension` appended, used for its lock and temporary
+    /// files.
+    fn sibling_file(&self, extension: &str) -> PathBuf {
+        let mut path = self.data_file.clone().into_os_string();
+        path.push(".");
+        path.push(extension);
+        PathBuf::from(path)
+    }
+
+    fn modified_at(data_file: &Path) -> Option<SystemTime> {
+        fs::metadata(data_file)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    fn load_from_file(data_file: &Path) -> io::Result<DataFile> {
+        let mut file = File::open(data_file)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
-        serde_json::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        serde_json::from_str(&contents)
+            .and_then(DataFile::migrate)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Adds the `--tag` and `--client` arguments read by `project_filter`.
+fn filter_args(command: Command) -> Command {
+    command
+        .arg(
+            arg!(--tag <TAG>)
+                .action(clap::ArgAction::Append)
+                .help("Only projects with this tag; can be given more than once to allow several"),
+        )
+        .arg(arg!(--client <CLIENT>).help("Only projects for this client"))
+}
+
+/// Reads the arguments added by `filter_args`.
+fn project_filter(matches: &ArgMatches) -> ProjectFilter {
+    ProjectFilter {
+        tags: matches
+            .get_many::<String>("tag")
+            .map(|tags| tags.cloned().collect())
+            .unwrap_or_default(),
+        client: matches.get_one::<String>("client").cloned(),
+    }
+}
+
+/// Adds the `--from`/`--to`/`--week`/`--month` arguments read by `date_range`.
+fn range_args(command: Command) -> Command {
+    let day_help = "a date in the configured format, an ISO 8601 date, \"today\", \"yesterday\", a weekday or an offset such as \"-3d\"";
+    command
+        .arg(
+            arg!(--from <DAY>)
+                .allow_hyphen_values(true)
+                .help(format!("First day to include: {}", day_help)),
+        )
+        .arg(
+            arg!(--to <DAY>)
+                .allow_hyphen_values(true)
+                .help(format!("Last day to include: {}", day_help)),
+        )
+        .arg(
+            arg!(--week[DAY])
+                .allow_hyphen_values(true)
+                .default_missing_value("today")
+                .conflicts_with_all(["from", "to", "month"])
+                .help(
+                    "Only the week (Monday to Sunday) containing DAY, the current one by default",
+                ),
+        )
+        .arg(
+            arg!(--month[DAY])
+                .allow_hyphen_values(true)
+                .default_missing_value("today")
+                .conflicts_with_all(["from", "to"])
+                .help("Only the month containing DAY, the current one by default"),
+        )
+}
+
+/// The `--date` argument of the commands that change one day, read by `day_or_today`.
+fn day_arg() -> clap::Arg {
+    arg!(--date <DAY>)
+        .allow_hyphen_values(true)
+        .help("Day to change, today by default: a date in the configured format, an ISO 8601 date, \"yesterday\", a weekday or an offset such as \"-3d\"")
+}
+
+/// Reads the argument added by `day_arg`.
+fn day_or_today(matches: &ArgMatches, date_format: &str) -> Result<NaiveDate, String> {
+    let today = Local::now().date_naive();
+    match matches.get_one::<String>("date") {
+        Some(day) => dates::day_arg(day, date_format, today),
+        None => Ok(today),
+    }
+}
+
+/// Reads a duration argument typed by the user.
+fn duration_arg(matches: &ArgMatches) -> Result<TrackedTime, String> {
+    let input = matches.get_one::<String>("DURATION").unwrap();
+    TrackedTime::parse_human(input).ok_or_else(|| {
+        format!(
+            "\u{26a0} Duration {:?} isn't something like \"1h30m\", \"45m\" or \"01:30:00\"!",
+            input
+        )
+    })
+}
+
+/// Value parser for hourly rates.
+fn parse_rate(input: &str) -> Result<f64, String> {
+    input
+        .parse::<f64>()
+        .ok()
+        .filter(|rate| rate.is_finite() && *rate >= 0.0)
+        .ok_or_else(|| String::from("expected an amount of zero or more, like \"85.50\""))
+}
+
+/// Value parser for percentages such as the tax of an invoice.
+fn parse_percent(input: &str) -> Result<f64, String> {
+    input
+        .parse::<f64>()
+        .ok()
+        .filter(|percent| percent.is_finite() && *percent >= 0.0)
+        .ok_or_else(|| String::from("expected a percentage of zero or more, like \"19\""))
+}
+
+/// Value parser for duration options.
+fn parse_duration(input: &str) -> Result<TrackedTime, String> {
+    TrackedTime::parse_human(input)
+        .ok_or_else(|| String::from("expected something like \"1h30m\", \"45m\" or \"01:30:00\""))
+}
+
+/// Reads the arguments of `add` into the start and end of the entry to record.
+fn added_span(
+    matches: &ArgMatches,
+    date_format: &str,
+) -> Result<(DateTime<Local>, DateTime<Local>), String> {
+    let date = day_or_today(matches, date_format)?;
+    let day_start = local_midnight(date);
+    let next_day = date.succ_opt().expect("date out of range");
+    let Some(from) = matches.get_one::<String>("from") else {
+        let end = duration_arg(matches)?
+            .to_delta()
+            .and_then(|duration| day_start.checked_add_signed(duration))
+            .filter(|end| *end <= local_midnight(next_day))
+            .ok_or_else(|| {
+                String::from(
+                    "\u{26a0} That's more time than the day has! Use --from and --to for work past midnight.",
+                )
+            })?;
+        return Ok((day_start, end));
+    };
+    let time = |input: &String| {
+        NaiveTime::parse_from_str(input, "%H:%M")
+            .or_else(|_| NaiveTime::parse_from_str(input, "%H:%M:%S"))
+            .map_err(|_| format!("\u{26a0} Time {:?} isn't like \"09:00\"!", input))
+    };
+    let (from, to) = (time(from)?, time(matches.get_one::<String>("to").unwrap())?);
+    let to_date = if to <= from { next_day } else { date };
+    let local = |date_time: NaiveDateTime| {
+        date_time
+            .and_local_timezone(Local)
+            .earliest()
+            .ok_or_else(|| format!("\u{26a0} {} doesn't exist in local time!", date_time))
+    };
+    Ok((local(date.and_time(from))?, local(to_date.and_time(to))?))
+}
+
+/// Reads the arguments added by `range_args` into the first and last day to include, where
+/// given.
+fn date_range(
+    matches: &ArgMatches,
+    date_format: &str,
+) -> Result<(Option<NaiveDate>, Option<NaiveDate>), String> {
+    let today = Local::now().date_naive();
+    let day = |name: &str| -> Result<Option<NaiveDate>, String> {
+        matches
+            .get_one::<String>(name)
+            .map(|day| dates::day_arg(day, date_format, today))
+            .transpose()
+    };
+    if let Some(date) = day("week")? {
+        let (from, to) = dates::week_of(date);
+        return Ok((Some(from), Some(to)));
+    }
+    if let Some(date) = day("month")? {
+        let (from, to) = dates::month_of(date);
+        return Ok((Some(from), Some(to)));
+    }
+    let (from, to) = (day("from")?, day("to")?);
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err(String::from(
+                "\u{26a0} The first day of the range comes after the last one!",
+            ));
+        }
+    }
+    Ok((from, to))
+}
+
+/// Completes a range read by `date_range` for commands covering a `period` (such as
+/// `dates::week_of`) by default: without a first day it starts with the period the last day
+/// falls in, without a last day it ends today, and without either it is the current period.
+fn range_or_period(
+    (from, to): (Option<NaiveDate>, Option<NaiveDate>),
+    period: fn(NaiveDate) -> (NaiveDate, NaiveDate),
+) -> Result<(NaiveDate, NaiveDate), String> {
+    let today = Local::now().date_naive();
+    let (from, to) = match (from, to) {
+        (Some(from), to) => (from, to.unwrap_or(today)),
+        (None, Some(to)) => (period(to).0, to),
+        (None, None) => period(today),
+    };
+    if from > to {
+        return Err(String::from(
+            "\u{26a0} The first day of the range comes after the last one!",
+        ));
     }
+    Ok((from, to))
 }
 
-impl Default for TimeTracking {
-    fn default() -> Self {
-        Self::new()
+/// Asks a yes/no question on the terminal. Anything but yes, including no terminal to ask on,
+/// is a no.
+fn confirm(question: &str) -> bool {
+    if !io::stdin().is_terminal() {
+        return false;
     }
+    print!("{} [y/N] ", question);
+    io::stdout().flush().expect("Failed to flush stdout");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap_or(0) > 0
+        && ["y", "yes"].contains(&input.trim().to_lowercase().as_str())
+}
+
+/// Moves a data file left in the current directory by older versions to `target`.
+fn move_data_file(target: &Path) -> io::Result<()> {
+    let source = Path::new(config::DATA_FILE_NAME);
+    if !source.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no {:?} in the current directory", config::DATA_FILE_NAME),
+        ));
+    }
+    if target.exists() {
+        if fs::canonicalize(source)? == fs::canonicalize(target)? {
+            return Ok(());
+        }
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{:?} already exists", target),
+        ));
+    }
+    if let Some(dir) = target.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    // Renaming fails across filesystems, e.g. from a mounted home directory into /tmp.
+    if fs::rename(source, target).is_err() {
+        fs::copy(source, target)?;
+        fs::remove_file(source)?;
+    }
+    Ok(())
 }
 
 fn main() {
-    terminal::enable_raw_mode().expect("Failed to enable raw mode");
-    let mut tt = TimeTracking::new();
     let matches = Command::new("Rust Time Tracker")
         .author("Rafael Zaccaro")
         .version("1.0.0")
         .about("Time tracker for projects")
         .subcommand_required(true)
         .arg_required_else_help(true)
+        .arg(
+            arg!(--"data-file" <PATH>)
+                .global(true)
+                .value_parser(clap::value_parser!(PathBuf))
+                .help(format!(
+                    "Data file to use instead of the one from ${} or the config file",
+                    config::DATA_FILE_ENV
+                )),
+        )
+        .arg(
+            arg!(--output <FORMAT>)
+                .global(true)
+                .value_parser(["table", "json", "plain"])
+                .default_value("table")
+                .help("How `status`, `list`, `day`, `report` and `goals` print their results: boxes, one JSON object with durations in seconds, or tab-separated lines"),
+        )
         .subcommand(
             Command::new("start")
                 .short_flag('s')
                 .about("Starts tracking a project")
-                .arg(arg!(<PROJECT_NAME>).help("Name of the project to start")),
+                .arg(arg!([PROJECT_NAME]).help(
+                    "Name of the project to start. Defaults to `default_project` from the config",
+                ))
+                .arg(
+                    arg!(--detach "Keeps tracking in the background instead of taking over the terminal"),
+                )
+                .arg(arg!(-m --message <NOTE>).help("Note on what the session is for, kept with the recorded time")),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Displays the project currently being tracked, if any")
+                .arg(
+                    arg!(--format <TEMPLATE>)
+                        .value_parser(status_bar::parse_template)
+                        .help("Prints one line for status bars instead, e.g. \"{project} {elapsed}\". Placeholders: {project}, {state}, {elapsed}, {today}, {started} and {note}"),
+                )
+                .arg(arg!(--waybar "Prints JSON for a waybar custom module, with the --format template as its text and a running, paused or idle class"))
+                .arg(
+                    arg!(--idle <TEXT>)
+                        .default_value("")
+                        .help("Text printed by --format and --waybar when nothing is being tracked"),
+                ),
+        )
+        .subcommand(Command::new("pause").about("Pauses the project currently being tracked"))
+        .subcommand(Command::new("resume").about("Resumes the paused project"))
+        .subcommand(
+            Command::new("stop").about("Stops tracking the current project and records its time"),
+        )
+        .subcommand(
+            Command::new("note")
+                .about("Displays or changes the note of the project currently being tracked")
+                .arg(arg!([NOTE]).help("New note for the session"))
+                .arg(
+                    arg!(--clear "Removes the note")
+                        .conflicts_with("NOTE"),
+                ),
         )
         .subcommand(
-            Command::new("list")
+            filter_args(Command::new("list"))
                 .short_flag('l')
                 .about(
                     "Displays tracking info of a project, if provided. If not, lists all projects",
@@ -586,87 +2071,1074 @@ fn main() {
                     arg!([PROJECT_NAME])
                         .required(false)
                         .help("Name of the project to retrieve tracking info from"),
-                ),
+                )
+                .arg(arg!(--all "Also lists archived projects")),
         )
         .subcommand(
-            Command::new("day")
+            filter_args(Command::new("day"))
                 .short_flag('d')
-                .about("Displays tracking info of a specific day (using the format \"mm/dd/yy\")")
+                .about("Displays tracking info of a specific day")
                 .arg(
                     arg!(<DAY>)
-                        .help("Day to retrieve tracking info from (using the format \"mm/dd/yy\")"),
+                        .allow_hyphen_values(true)
+                        .help("Day to retrieve tracking info from: a date in the configured format (\"mm/dd/yy\" by default), an ISO 8601 date, \"today\", \"yesterday\", a weekday such as \"monday\" or an offset such as \"-3d\""),
+                ),
+        )
+        .subcommand(
+            range_args(filter_args(
+                Command::new("report")
+                    .short_flag('r')
+                    .about("Displays per-project, per-client and per-day totals for a range of days (the current week by default)"),
+            )),
+        )
+        .subcommand(
+            Command::new("rename")
+                .about("Renames a project")
+                .arg(arg!(<PROJECT_NAME>).help("Current name of the project"))
+                .arg(arg!(<NEW_NAME>).help("New name of the project")),
+        )
+        .subcommand(
+            Command::new("delete")
+                .about("Deletes a project and all the time tracked on it")
+                .arg(arg!(<PROJECT_NAME>).help("Project to delete"))
+                .arg(arg!(-y --yes "Deletes without asking for confirmation")),
+        )
+        .subcommand(
+            Command::new("archive")
+                .about("Hides a project from `list` without deleting it")
+                .arg(arg!(<PROJECT_NAME>).help("Project to archive")),
+        )
+        .subcommand(
+            Command::new("unarchive")
+                .about("Lists an archived project again")
+                .arg(arg!(<PROJECT_NAME>).help("Project to unarchive")),
+        )
+        .subcommand(
+            Command::new("tag")
+                .about("Adds tags to a project, or removes them")
+                .arg(arg!(<PROJECT_NAME>).help("Project to tag"))
+                .arg(arg!(<TAG>...).help("Tags to add or remove"))
+                .arg(arg!(--remove "Removes the tags instead of adding them")),
+        )
+        .subcommand(
+            Command::new("client")
+                .about("Sets the client a project is for")
+                .arg(arg!(<PROJECT_NAME>).help("Project to change"))
+                .arg(arg!([CLIENT]).help("Client of the project. Leave out to clear it")),
+        )
+        .subcommand(
+            Command::new("rate")
+                .about("Sets the hourly rate billed by `invoice` for a project or a client")
+                .arg(arg!(<NAME>).help("Project, or client with --client"))
+                .arg(
+                    arg!([RATE])
+                        .value_parser(parse_rate)
+                        .help("Amount billed per hour, e.g. \"85.50\""),
+                )
+                .arg(arg!(--currency <CURRENCY>).help(
+                    "Currency of the rate, e.g. \"EUR\". Defaults to the client's, then to `currency` from the config",
+                ))
+                .arg(arg!(--client "Sets the default for every project of client NAME instead"))
+                .arg(
+                    arg!(--clear "Removes the rate and currency")
+                        .conflicts_with_all(["RATE", "currency"]),
                 ),
         )
+        .subcommand(
+            Command::new("budget")
+                .about("Sets how much time a project is allotted")
+                .arg(arg!(<PROJECT_NAME>).help("Project to budget"))
+                .arg(
+                    arg!(--total <DURATION>)
+                        .value_parser(parse_duration)
+                        .help("Time allotted overall, e.g. \"40h\""),
+                )
+                .arg(
+                    arg!(--weekly <DURATION>)
+                        .value_parser(parse_duration)
+                        .help("Time allotted each week, Monday to Sunday, e.g. \"8h\""),
+                )
+                .arg(
+                    arg!(--clear "Removes the budgets")
+                        .conflicts_with_all(["total", "weekly"]),
+                ),
+        )
+        .subcommand(
+            Command::new("merge")
+                .about("Moves all the time tracked on one project into another and deletes the first")
+                .arg(arg!(<SOURCE>).help("Project to merge and delete"))
+                .arg(arg!(<TARGET>).help("Project to merge into")),
+        )
+        .subcommand(
+            Command::new("add")
+                .about("Adds time to a project, for work that wasn't tracked")
+                .arg(arg!(<PROJECT_NAME>).help("Project to add the time to"))
+                .arg(
+                    arg!([DURATION])
+                        .required_unless_present("from")
+                        .help("Time to add at the start of the day, e.g. \"1h30m\", \"45m\" or \"01:30:00\""),
+                )
+                .arg(
+                    arg!(--from <TIME>)
+                        .requires("to")
+                        .conflicts_with("DURATION")
+                        .help("Time the work started, e.g. \"09:00\""),
+                )
+                .arg(arg!(--to <TIME>).requires("from").help(
+                    "Time the work ended, e.g. \"11:30\". Earlier than --from means the next day",
+                ))
+                .arg(day_arg()),
+        )
+        .subcommand(
+            Command::new("subtract")
+                .about("Removes time from a project, shortening the day's latest entries first")
+                .arg(arg!(<PROJECT_NAME>).help("Project to remove the time from"))
+                .arg(arg!(<DURATION>).help("Time to remove, e.g. \"30m\""))
+                .arg(day_arg()),
+        )
+        .subcommand(
+            Command::new("edit")
+                .about("Sets the time tracked on a project for a day")
+                .arg(arg!(<PROJECT_NAME>).help("Project to correct"))
+                .arg(arg!(<DURATION>).help("Total time for the day, e.g. \"6h\""))
+                .arg(day_arg()),
+        )
+        .subcommand(
+            range_args(filter_args(
+                Command::new("export")
+                    .about("Writes tracked time as CSV, one row per project and day")
+                    .arg(
+                        arg!(--format <FORMAT>)
+                            .value_parser(["csv"])
+                            .default_value("csv")
+                            .help("Output format"),
+                    )
+                    .arg(
+                        arg!(--project <NAME>)
+                            .action(clap::ArgAction::Append)
+                            .help("Only export this project; can be given more than once"),
+                    )
+                    .arg(arg!(--entries "Writes one row per recorded entry and day instead"))
+                    .arg(
+                        arg!(--file <PATH>)
+                            .value_parser(clap::value_parser!(PathBuf))
+                            .help("File to write to instead of standard output"),
+                    ),
+            )),
+        )
+        .subcommand(
+            range_args(filter_args(
+                Command::new("invoice")
+                    .about("Writes an itemized invoice of the time tracked in a range of days (the current month by default)")
+                    .arg(
+                        arg!(--format <FORMAT>)
+                            .value_parser(["text", "markdown", "html"])
+                            .default_value("text")
+                            .help("Output format"),
+                    )
+                    .arg(
+                        arg!(--project <NAME>)
+                            .action(clap::ArgAction::Append)
+                            .help("Only bill this project; can be given more than once"),
+                    )
+                    .arg(
+                        arg!(--round <MINUTES>)
+                            .value_parser(clap::value_parser!(u32))
+                            .help("Rounds each day's time on a project up to a multiple of MINUTES, e.g. 6 or 15"),
+                    )
+                    .arg(
+                        arg!(--tax <PERCENT>)
+                            .value_parser(parse_percent)
+                            .help("Adds a tax line of PERCENT of the subtotal"),
+                    )
+                    .arg(
+                        arg!(--file <PATH>)
+                            .value_parser(clap::value_parser!(PathBuf))
+                            .help("File to write to instead of standard output"),
+                    ),
+            )),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Adds the entries from a Toggl, Clockify or other CSV export")
+                .arg(
+                    arg!(<FILE>)
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("CSV file to import"),
+                )
+                .arg(
+                    arg!(--format <FORMAT>)
+                        .required(true)
+                        .value_parser(["toggl", "clockify", "csv"])
+                        .help("Tool the file was exported from, or \"csv\" to map the columns with --map"),
+                )
+                .arg(
+                    arg!(--map <MAPPING>)
+                        .required_if_eq("format", "csv")
+                        .help("Columns to read, e.g. \"project=Client,start=Began,duration=Hours\". Fields: project, note, date, start, end_date, end and duration"),
+                )
+                .arg(
+                    arg!(--"date-format" <FORMAT>)
+                        .help("`chrono` format of the dates in the file, if not ISO 8601, mm/dd/yyyy or the configured one"),
+                )
+                .arg(arg!(--"dry-run" "Only displays what would be imported")),
+        )
+        .subcommand(Command::new("goals").about(
+            "Displays progress towards the daily and weekly goals from the config, and streaks",
+        ))
+        .subcommand(
+            Command::new("repair")
+                .about("Recomputes daily and total times from the recorded entries and saves them"),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Displays or changes settings in the config file")
+                .subcommand_required(true)
+                .subcommand(Command::new("show").about("Displays every setting"))
+                .subcommand(
+                    Command::new("get")
+                        .about("Displays one setting")
+                        .arg(arg!(<KEY>).help("Setting to display, e.g. \"keys.pause\"")),
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about("Changes one setting")
+                        .arg(arg!(<KEY>).help("Setting to change, e.g. \"date_format\""))
+                        .arg(arg!(<VALUE>).help("New value, or \"\" to restore the default")),
+                ),
+        )
+        .subcommand(
+            Command::new("move-data").about(
+                "Moves the data file from the current directory to the configured data location",
+            ),
+        )
         .get_matches(); //hint format to user
 
+    let config = Config::load().unwrap_or_else(|e| {
+        println!(
+            "{}",
+            format!("\u{26a0} Unable to read config file: {}", e).red()
+        );
+        // `config set` edits the file as it is, so it can still repair it.
+        match matches.subcommand() {
+            Some(("config", sub_matches)) if sub_matches.subcommand_name() == Some("set") => {
+                Config::default()
+            }
+            _ => process::exit(1),
+        }
+    });
+    if let Some(("config", sub_matches)) = matches.subcommand() {
+        let mut config = config;
+        match sub_matches.subcommand() {
+            Some(("show", _)) => {
+                if let Some(path) = Config::path() {
+                    println!(
+                        "{}",
+                        format!("\u{1f6c8} Settings from {:?}", path).with(config.colors.info())
+                    );
+                }
+                print!("{}", config.show());
+            }
+            Some(("get", sub_matches)) => {
+                let key = sub_matches.get_one::<String>("KEY").unwrap();
+                match config.get(key) {
+                    Some(value) => println!("{}", value),
+                    None if config::KEYS.contains(&key.as_str()) => println!(
+                        "{}",
+                        format!("\u{1f6c8} {:?} is not set", key).with(config.colors.info())
+                    ),
+                    None => println!(
+                        "{}",
+                        format!("\u{26a0} Unknown setting {:?}", key).with(config.colors.warning())
+                    ),
+                }
+            }
+            Some(("set", sub_matches)) => {
+                let key = sub_matches.get_one::<String>("KEY").unwrap();
+                let value = sub_matches.get_one::<String>("VALUE").unwrap();
+                match config.set(key, value) {
+                    Ok(()) => println!(
+                        "{}",
+                        format!("\u{2714} Set {:?} to {:?}", key, value)
+                            .with(config.colors.success())
+                    ),
+                    Err(e) => println!(
+                        "{}",
+                        format!("\u{26a0} Unable to change setting: {}", e)
+                            .with(config.colors.warning())
+                    ),
+                }
+            }
+            _ => unreachable!("subcommand_required prevents `None`"),
+        }
+        return;
+    }
+
+    let data_file = config.data_file(
+        matches
+            .get_one::<PathBuf>("data-file")
+            .map(PathBuf::as_path),
+    );
+
+    if let Some(("move-data", _)) = matches.subcommand() {
+        match move_data_file(&data_file) {
+            Ok(()) => println!(
+                "{}",
+                format!("\u{2714} Data file is now at {:?}", data_file)
+                    .with(config.colors.success())
+            ),
+            Err(e) => println!(
+                "{}",
+                format!("\u{26a0} Unable to move data file: {}", e).with(config.colors.warning())
+            ),
+        }
+        return;
+    }
+
+    let mut tt = match TimeTracking::new(data_file.clone(), config.clone()) {
+        Ok(tt) => tt,
+        Err(e) => {
+            println!(
+                "{}",
+                format!(
+                    "\u{26a0} Unable to read {:?}: {}. Fix or move the file; it will not be overwritten.",
+                    data_file, e
+                )
+                .with(config.colors.warning())
+            );
+            process::exit(1);
+        }
+    };
+
+    tt.output = Output::from_name(matches.get_one::<String>("output").unwrap());
+    // Scripts and status bars reading the output can't answer the recovery question, and
+    // commands writing data to stdout must not have it mixed in.
+    let data_on_stdout = matches
+        .subcommand()
+        .is_some_and(|(command, sub_matches)| match command {
+            "status" => sub_matches.contains_id("format") || sub_matches.get_flag("waybar"),
+            "export" | "invoice" => !sub_matches.contains_id("file"),
+            _ => false,
+        });
+    // Older versions kept the data file in the current directory; without this, moving to the
+    // data directory would look like all the tracked time was gone.
+    if !data_file.exists() && Path::new(config::DATA_FILE_NAME).exists() {
+        let warning = format!(
+            "\u{26a0} No data file at {:?} yet, but an older {:?} is in the current directory. Run `move-data` to keep using it.",
+            data_file,
+            config::DATA_FILE_NAME
+        );
+        if data_on_stdout {
+            eprintln!("{}", warning);
+        } else {
+            tt.warn(&warning);
+        }
+    }
+    if tt.output == Output::Table && !data_on_stdout {
+        tt.recover_orphaned_session();
+    }
+
     match matches.subcommand() {
         Some(("start", sub_matches)) => {
-            tt.start_project(sub_matches.get_one::<String>("PROJECT_NAME").unwrap());
-            loop {
+            let Some(project_name) = sub_matches
+                .get_one::<String>("PROJECT_NAME")
+                .or(tt.config.default_project.as_ref())
+                .cloned()
+            else {
+                println!(
+                    "{}",
+                    "\u{26a0} No project given and no `default_project` configured."
+                        .with(tt.config.colors.warning())
+                );
+                return;
+            };
+            let project_name = &project_name;
+            let note = sub_matches.get_one::<String>("message").map(String::as_str);
+            let detach = sub_matches.get_flag("detach");
+            let attach = !detach
+                && tt
+                    .current_session
+                    .as_ref()
+                    .is_some_and(|session| session.project == *project_name);
+            if detach {
+                tt.start_project(project_name, note);
+                return;
+            }
+
+            let interrupted = Arc::new(AtomicBool::new(false));
+            let flag = interrupted.clone();
+            ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst))
+                .expect("Failed to set signal handler");
+            let default_hook = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                Dashboard::restore_terminal();
+                default_hook(info);
+            }));
+
+            tt.interactive = true;
+            if attach {
+                tt.info(
+                    &format!("\u{2714} Attached to running project: {:?}.", project_name),
+                    tt.config.colors.success(),
+                );
+                if let Some(note) = note {
+                    tt.set_note(Some(note.to_string()));
+                }
+            } else if !tt.start_project(project_name, note) {
+                tt.print_messages();
+                return;
+            }
+            tt.checkpoint();
+            let mut dashboard = Dashboard::open().expect("Failed to open the dashboard");
+            let mut last_checkpoint = Instant::now();
+            let mut budget_alerted = Vec::new();
+            let mut idle = IdleWatch::new(&tt.config.idle);
+            let farewell = loop {
                 if poll(Duration::from_millis(100)).expect("Failed to poll events") {
-                    if let Ok(Event::Key(KeyEvent {
-                        code,
-                        state: _,
-                        modifiers: _,
-                        kind,
-                    })) = read()
-                    {
-                        match code {
-                            KeyCode::Char('a') => {
+                    match read() {
+                        Ok(Event::Key(key))
+                            if idle.as_mut().is_some_and(|idle| {
+                                idle.handle_key(&mut tt, &mut dashboard, key)
+                            }) => {}
+                        // An open dialog takes every key, while the loop around it keeps the
+                        // session checkpointed and up to date.
+                        Ok(Event::Key(key)) if dashboard.dialog_open() => {
+                            match dashboard.handle_key(key) {
+                                Some((Dialog::Switch, Some(name))) => tt.switch_project(&name),
+                                Some((Dialog::GiveIdleTime, Some(name))) => {
+                                    if let Some(ref mut idle) = idle {
+                                        idle.give_away(&mut tt, &name);
+                                    }
+                                }
+                                Some((Dialog::Note, Some(note))) => match note.as_str() {
+                                    "" => {}
+                                    "-" => {
+                                        tt.set_note(None);
+                                    }
+                                    note => {
+                                        tt.set_note(Some(note.to_string()));
+                                    }
+                                },
+                                Some((_, None)) | None => {}
+                            }
+                        }
+                        Ok(Event::Key(KeyEvent {
+                            code,
+                            state: _,
+                            modifiers,
+                            kind,
+                        })) => match code {
+                            // Raw mode swallows SIGINT, so Ctrl-C arrives as a key press.
+                            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                                interrupted.store(true, Ordering::SeqCst);
+                            }
+                            KeyCode::Char(key) if key == tt.config.keys.stop => {
+                                let stopped = tt.current_session.clone();
                                 tt.stop_project();
-                                //maybe print out something like "today's total time {}, project total time {}"
-                                break;
+                                let message = match stopped {
+                                    Some(session) => format!(
+                                        "\u{2714} Stopped project {:?} after {}.",
+                                        session.project,
+                                        TrackedTime::from(session.elapsed())
+                                    ),
+                                    None => String::from("\u{2714} Stopped."),
+                                };
+                                break (message, tt.config.colors.success());
                             }
-                            KeyCode::Char('s') if kind == crossterm::event::KeyEventKind::Press => {
-                                tt.switch_project(&get_input("\u{1f5cb} New project name: "));
+                            KeyCode::Char(key)
+                                if key == tt.config.keys.switch
+                                    && kind == crossterm::event::KeyEventKind::Press =>
+                            {
+                                dashboard.pick_project(&tt, Dialog::Switch);
                             }
-                            KeyCode::Char('p') if kind == crossterm::event::KeyEventKind::Press => {
+                            KeyCode::Char(key)
+                                if key == tt.config.keys.pause
+                                    && kind == crossterm::event::KeyEventKind::Press =>
+                            {
                                 tt.pause();
                             }
-                            KeyCode::Char('r') if kind == crossterm::event::KeyEventKind::Press => {
+                            KeyCode::Char(key)
+                                if key == tt.config.keys.resume
+                                    && kind == crossterm::event::KeyEventKind::Press =>
+                            {
                                 tt.resume();
                             }
+                            KeyCode::Char(key)
+                                if key == tt.config.keys.note
+                                    && kind == crossterm::event::KeyEventKind::Press =>
+                            {
+                                let current = tt
+                                    .current_session
+                                    .as_ref()
+                                    .and_then(|session| session.note.clone());
+                                let prompt = match current {
+                                    Some(ref note) => format!(
+                                        "\u{1f5c9} Note (empty keeps {:?}, \"-\" removes it): ",
+                                        note
+                                    ),
+                                    None => String::from("\u{1f5c9} Note: "),
+                                };
+                                dashboard.prompt(Dialog::Note, prompt);
+                            }
                             _ => {}
-                        }
+                        },
+                        Ok(Event::Resize(..)) => dashboard.invalidate(),
+                        _ => {}
                     }
                 }
-                if let Some(ref current_project) = tt.current_project {
-                    if let Some(ref mut current_session) = tt.current_session {
-                        if !current_session.paused {
-                            current_session.elapsed += 1;
-                            execute!(
-                                stdout(),
-                                terminal::Clear(terminal::ClearType::CurrentLine),
-                                cursor::MoveToColumn(0),
-                                Print(format!(
-                                    "\u{23f1} Project: {} \u{2016} Elapsed time: {}",
-                                    current_project.name,
-                                    current_session.format_elapsed()
-                                ))
-                            )
-                            .expect("f");
-                        }
+                // A file that fails to load is reported by the next write, which needs it.
+                let _ = tt.reload_if_changed();
+                if interrupted.load(Ordering::SeqCst) {
+                    if attach {
+                        tt.detach();
+                        break (
+                            String::from(
+                                "\u{1f6c8} Detached. The project keeps tracking in the background.",
+                            ),
+                            tt.config.colors.info(),
+                        );
                     }
+                    tt.checkpoint();
+                    break (
+                        String::from("\u{26a0} Interrupted. The session was saved and can be recovered on the next run."),
+                        tt.config.colors.warning(),
+                    );
                 }
-                thread::sleep(Duration::from_secs(1));
-            }
-            terminal::disable_raw_mode().expect("Failed to disable raw mode");
+                if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                    tt.checkpoint();
+                    last_checkpoint = Instant::now();
+                }
+                if tt.current_session.is_none() {
+                    break (
+                        String::from("\u{1f6c8} Project was stopped from another shell."),
+                        tt.config.colors.info(),
+                    );
+                }
+                if let Some(ref mut idle) = idle {
+                    idle.check(&mut tt);
+                    dashboard.question = idle.question(&tt.config.keys);
+                }
+                for (key, alert) in tt.budget_alerts() {
+                    if !budget_alerted.contains(&key) {
+                        tt.info(&alert, tt.config.colors.warning());
+                        budget_alerted.push(key);
+                    }
+                }
+                dashboard.draw(&tt).expect("Failed to draw the dashboard");
+            };
+            drop(dashboard);
+            tt.interactive = false;
+            println!("{}", farewell.0.with(farewell.1));
         }
         Some(("list", sub_matches)) => match sub_matches.get_one::<String>("PROJECT_NAME") {
             Some(proj) => {
-                tt.list_project_or_all(Some(proj));
+                tt.list_project_or_all(Some(proj), true, &ProjectFilter::default());
             }
             None => {
+                if tt.output == Output::Table {
+                    println!(
+                        "{}",
+                        "\u{1f6c8} Displaying tracking information for all projects"
+                            .with(tt.config.colors.info())
+                    );
+                }
+                tt.list_project_or_all(
+                    None,
+                    sub_matches.get_flag("all"),
+                    &project_filter(sub_matches),
+                );
+            }
+        },
+        Some(("day", sub_matches)) => {
+            tt.get_day_info(
+                sub_matches.get_one::<String>("DAY").unwrap(),
+                &project_filter(sub_matches),
+            );
+        }
+        Some(("report", sub_matches)) => match date_range(sub_matches, &tt.config.date_format)
+            .and_then(|range| range_or_period(range, dates::week_of))
+        {
+            Ok((from, to)) => tt.report(from, to, &project_filter(sub_matches)),
+            Err(e) => tt.warn(&e),
+        },
+        Some((
+            command @ ("rename" | "delete" | "tag" | "client" | "rate" | "budget" | "archive"
+            | "unarchive" | "merge"),
+            sub_matches,
+        )) => {
+            let arg = |name: &str| sub_matches.get_one::<String>(name).unwrap().as_str();
+            let result = match command {
+                "rename" => tt.rename_project(arg("PROJECT_NAME"), arg("NEW_NAME")),
+                "delete" => {
+                    let name = arg("PROJECT_NAME");
+                    let total = tt.projects.get(name).map(|project| project.total_time);
+                    if total.is_none()
+                        || sub_matches.get_flag("yes")
+                        || confirm(&format!(
+                            "Delete project {:?} and its {} of tracked time?",
+                            name,
+                            total.unwrap_or_default()
+                        ))
+                    {
+                        tt.delete_project(name)
+                    } else {
+                        println!(
+                            "{}",
+                            format!(
+                                "\u{1f6c8} Kept project {:?}. Pass --yes to delete it without being asked.",
+                                name
+                            )
+                            .with(tt.config.colors.info())
+                        );
+                        Ok(())
+                    }
+                }
+                "tag" => tt.tag_project(
+                    arg("PROJECT_NAME"),
+                    sub_matches
+                        .get_many::<String>("TAG")
+                        .unwrap()
+                        .cloned()
+                        .collect(),
+                    sub_matches.get_flag("remove"),
+                ),
+                "client" => tt.set_client(
+                    arg("PROJECT_NAME"),
+                    sub_matches.get_one::<String>("CLIENT").cloned(),
+                ),
+                "rate" => tt.set_rate(
+                    arg("NAME"),
+                    sub_matches.get_one::<f64>("RATE").copied(),
+                    sub_matches.get_one::<String>("currency").cloned(),
+                    sub_matches.get_flag("client"),
+                    sub_matches.get_flag("clear"),
+                ),
+                "budget" => tt.set_budget(
+                    arg("PROJECT_NAME"),
+                    sub_matches.get_one::<TrackedTime>("total").copied(),
+                    sub_matches.get_one::<TrackedTime>("weekly").copied(),
+                    sub_matches.get_flag("clear"),
+                ),
+                "archive" => tt.set_archived(arg("PROJECT_NAME"), true),
+                "unarchive" => tt.set_archived(arg("PROJECT_NAME"), false),
+                _ => tt.merge_projects(arg("SOURCE"), arg("TARGET")),
+            };
+            if let Err(e) = result {
                 println!(
                     "{}",
-                    "\u{1f6c8} Displaying tracking information for all projects".blue()
+                    format!("\u{26a0} Unable to {} project: {}", command, e)
+                        .with(tt.config.colors.warning())
                 );
-                tt.list_project_or_all(None);
             }
+        }
+        Some(("add", sub_matches)) => {
+            let project = sub_matches.get_one::<String>("PROJECT_NAME").unwrap();
+            match added_span(sub_matches, &tt.config.date_format) {
+                Ok((start, end)) => {
+                    if let Err(e) = tt.add_time(project, start, end) {
+                        println!(
+                            "{}",
+                            format!("\u{26a0} Unable to add time: {}", e)
+                                .with(tt.config.colors.warning())
+                        );
+                    }
+                }
+                Err(e) => println!("{}", e.with(tt.config.colors.warning())),
+            }
+        }
+        Some((command @ ("subtract" | "edit"), sub_matches)) => {
+            let project = sub_matches.get_one::<String>("PROJECT_NAME").unwrap();
+            let args = day_or_today(sub_matches, &tt.config.date_format)
+                .and_then(|date| Ok((date, duration_arg(sub_matches)?)));
+            match args {
+                Ok((date, duration)) => {
+                    let result = if command == "subtract" {
+                        tt.subtract_time(project, date, duration)
+                    } else {
+                        tt.set_day_time(project, date, duration)
+                    };
+                    if let Err(e) = result {
+                        println!(
+                            "{}",
+                            format!("\u{26a0} Unable to {} time: {}", command, e)
+                                .with(tt.config.colors.warning())
+                        );
+                    }
+                }
+                Err(e) => println!("{}", e.with(tt.config.colors.warning())),
+            }
+        }
+        Some(("export", sub_matches)) => match date_range(sub_matches, &tt.config.date_format) {
+            Ok((from, to)) => {
+                let filter = export::ExportFilter {
+                    projects: sub_matches
+                        .get_many::<String>("project")
+                        .map(|names| names.cloned().collect())
+                        .unwrap_or_default(),
+                    from,
+                    to,
+                    entries: sub_matches.get_flag("entries"),
+                    project_filter: project_filter(sub_matches),
+                };
+                let result = match sub_matches.get_one::<PathBuf>("file") {
+                    Some(path) => File::create(path)
+                        .map_err(csv::Error::from)
+                        .and_then(|file| tt.export_csv(file, &filter)),
+                    None => tt.export_csv(stdout().lock(), &filter),
+                };
+                if let Err(e) = result {
+                    println!(
+                        "{}",
+                        format!("\u{26a0} Unable to export: {}", e)
+                            .with(tt.config.colors.warning())
+                    );
+                }
+            }
+            Err(e) => println!("{}", e.with(tt.config.colors.warning())),
         },
-        Some(("day", sub_matches)) => {
-            tt.get_day_info(sub_matches.get_one::<String>("DAY").unwrap());
+        Some(("invoice", sub_matches)) => {
+            let result = date_range(sub_matches, &tt.config.date_format)
+                .and_then(|range| range_or_period(range, dates::month_of))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+                .and_then(|(from, to)| {
+                    tt.invoice(&invoice::InvoiceOptions {
+                        from,
+                        to,
+                        projects: sub_matches
+                            .get_many::<String>("project")
+                            .map(|names| names.cloned().collect())
+                            .unwrap_or_default(),
+                        filter: project_filter(sub_matches),
+                        rounding: sub_matches.get_one::<u32>("round").copied(),
+                        tax: sub_matches.get_one::<f64>("tax").copied(),
+                        format: match sub_matches.get_one::<String>("format").unwrap().as_str() {
+                            "markdown" => invoice::InvoiceFormat::Markdown,
+                            "html" => invoice::InvoiceFormat::Html,
+                            _ => invoice::InvoiceFormat::Text,
+                        },
+                    })
+                })
+                .and_then(|invoice| match sub_matches.get_one::<PathBuf>("file") {
+                    Some(path) => fs::write(path, invoice),
+                    None => {
+                        print!("{}", invoice);
+                        Ok(())
+                    }
+                });
+            if let Err(e) = result {
+                println!(
+                    "{}",
+                    format!("\u{26a0} Unable to write invoice: {}", e)
+                        .with(tt.config.colors.warning())
+                );
+            }
+        }
+        Some(("import", sub_matches)) => {
+            let columns = match sub_matches.get_one::<String>("format").unwrap().as_str() {
+                "toggl" => Ok(import::ColumnMap::toggl()),
+                "clockify" => Ok(import::ColumnMap::clockify()),
+                _ => import::ColumnMap::parse(sub_matches.get_one::<String>("map").unwrap()),
+            };
+            let result = columns
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+                .and_then(|columns| {
+                    tt.import(
+                        sub_matches.get_one::<PathBuf>("FILE").unwrap(),
+                        &columns,
+                        sub_matches
+                            .get_one::<String>("date-format")
+                            .map(String::as_str),
+                        sub_matches.get_flag("dry-run"),
+                    )
+                });
+            if let Err(e) = result {
+                println!(
+                    "{}",
+                    format!("\u{26a0} Unable to import: {}", e).with(tt.config.colors.warning())
+                );
+            }
+        }
+        Some(("status", sub_matches)) => {
+            let template = sub_matches.get_one::<String>("format").map(String::as_str);
+            let idle = sub_matches.get_one::<String>("idle").unwrap();
+            if sub_matches.get_flag("waybar") {
+                tt.status_waybar(template.unwrap_or(status_bar::DEFAULT_TEMPLATE), idle);
+            } else if let Some(template) = template {
+                tt.status_line(template, idle);
+            } else {
+                tt.status();
+            }
+        }
+        Some(("goals", _)) => tt.goals(),
+        Some(("note", sub_matches)) => {
+            if sub_matches.get_flag("clear") {
+                tt.set_note(None);
+            } else if let Some(note) = sub_matches.get_one::<String>("NOTE") {
+                tt.set_note(Some(note.clone()));
+            } else {
+                match tt.current_session {
+                    Some(Session {
+                        note: Some(ref note),
+                        ..
+                    }) => println!("\u{1f5c9} {}", note),
+                    Some(ref session) => println!(
+                        "{}",
+                        format!("\u{1f6c8} Project {:?} has no note.", session.project)
+                            .with(tt.config.colors.info())
+                    ),
+                    None => println!(
+                        "{}",
+                        "\u{26a0} No project is being tracked.".with(tt.config.colors.warning())
+                    ),
+                }
+            }
+        }
+        Some(("pause", _)) => tt.pause(),
+        Some(("resume", _)) => tt.resume(),
+        Some(("stop", _)) => match tt.current_session.clone() {
+            Some(session) => {
+                tt.stop_project();
+                println!(
+                    "{}",
+                    format!(
+                        "\u{2714} Stopped project {:?} after {}.",
+                        session.project,
+                        session.format_elapsed()
+                    )
+                    .with(tt.config.colors.success())
+                );
+            }
+            None => println!(
+                "{}",
+                "\u{26a0} No project is being tracked.".with(tt.config.colors.warning())
+            ),
+        },
+        Some(("repair", _)) => {
+            if let Err(e) = tt.repair() {
+                println!(
+                    "{}",
+                    format!("\u{26a0} Unable to repair data file: {}", e)
+                        .with(tt.config.colors.warning())
+                );
+            }
         }
         _ => unreachable!("Exhausted list of subcommands and subcommand_required prevents `None`"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, sync::Once};
+
+    /// Makes local time Europe/Berlin, which switches to summer time on 2024-03-31 at 02:00 and
+    /// back on 2024-10-27 at 03:00. Every test depending on local time uses the same zone, so
+    /// the tests can run in parallel.
+    fn berlin() {
+        static TZ: Once = Once::new();
+        TZ.call_once(|| env::set_var("TZ", "Europe/Berlin"));
+    }
+
+    fn at(moment: &str) -> DateTime<Local> {
+        DateTime::parse_from_rfc3339(moment)
+            .expect("valid RFC 3339 moment")
+            .with_timezone(&Local)
+    }
+
+    fn date(day: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(day, DAY_KEY_FORMAT).expect("valid day")
+    }
+
+    fn entry(start: &str, end: &str, pauses: &[(&str, &str)]) -> TimeEntry {
+        TimeEntry {
+            project: String::from("p"),
+            start: at(start),
+            end: at(end),
+            pauses: pauses
+                .iter()
+                .map(|(start, end)| Pause {
+                    start: at(start),
+                    end: Some(at(end)),
+                })
+                .collect(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn local_midnight_bounds_days_of_23_and_25_hours() {
+        berlin();
+        assert_eq!(
+            local_midnight(date("2024-03-31")),
+            at("2024-03-31T00:00:00+01:00")
+        );
+        assert_eq!(
+            local_midnight(date("2024-04-01")) - local_midnight(date("2024-03-31")),
+            TimeDelta::hours(23)
+        );
+        assert_eq!(
+            local_midnight(date("2024-10-28")) - local_midnight(date("2024-10-27")),
+            TimeDelta::hours(25)
+        );
+        assert_eq!(
+            local_midnight(date("2024-07-02")) - local_midnight(date("2024-07-01")),
+            TimeDelta::hours(24)
+        );
+    }
+
+    #[test]
+    fn time_per_day_within_one_day() {
+        berlin();
+        let entry = entry(
+            "2024-03-31T10:00:00+02:00",
+            "2024-03-31T11:30:00+02:00",
+            &[],
+        );
+        assert_eq!(
+            entry.time_per_day(),
+            vec![(date("2024-03-31"), TimeDelta::minutes(90))]
+        );
+        let empty = TimeEntry {
+            end: entry.start,
+            ..entry
+        };
+        assert_eq!(
+            empty.time_per_day(),
+            vec![(date("2024-03-31"), TimeDelta::zero())]
+        );
+    }
+
+    #[test]
+    fn time_per_day_across_spring_forward() {
+        berlin();
+        let entry = entry(
+            "2024-03-30T22:00:00+01:00",
+            "2024-04-01T01:00:00+02:00",
+            &[],
+        );
+        assert_eq!(
+            entry.time_per_day(),
+            vec![
+                (date("2024-03-30"), TimeDelta::hours(2)),
+                (date("2024-03-31"), TimeDelta::hours(23)),
+                (date("2024-04-01"), TimeDelta::hours(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn time_per_day_across_fall_back() {
+        berlin();
+        let entry = entry(
+            "2024-10-26T23:00:00+02:00",
+            "2024-10-28T00:30:00+01:00",
+            &[],
+        );
+        assert_eq!(
+            entry.time_per_day(),
+            vec![
+                (date("2024-10-26"), TimeDelta::hours(1)),
+                (date("2024-10-27"), TimeDelta::hours(25)),
+                (date("2024-10-28"), TimeDelta::minutes(30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn time_per_day_leaves_out_pauses_over_midnight() {
+        berlin();
+        let entry = entry(
+            "2024-03-30T23:00:00+01:00",
+            "2024-03-31T03:30:00+02:00",
+            &[("2024-03-30T23:30:00+01:00", "2024-03-31T00:30:00+01:00")],
+        );
+        assert_eq!(
+            entry.time_per_day(),
+            vec![
+                (date("2024-03-30"), TimeDelta::minutes(30)),
+                (date("2024-03-31"), TimeDelta::hours(2)),
+            ]
+        );
+        assert_eq!(entry.duration(), TimeDelta::minutes(150));
+    }
+
+    #[test]
+    fn migrate_v1_to_v3() {
+        berlin();
+        let v1 = serde_json::json!({
+            "testshort": {
+                "name": "t",
+                "start_date": "01/30/24 06:45:31",
+                "hours_per_day": { "01/30/24": "00:07:42", "01/31/24": "01:00:00" },
+                "total_time": "01:07:42"
+            }
+        });
+        let data = DataFile::migrate(v1).expect("version 1 migrates");
+        assert_eq!(data.version, DATA_VERSION);
+        let project = &data.projects["testshort"];
+        assert_eq!(project.start_date, "2024-01-30T06:45:31");
+        let mut days: Vec<_> = project.hours_per_day.iter().collect();
+        days.sort();
+        assert_eq!(
+            days,
+            [
+                (&String::from("2024-01-30"), &"00:07:42".parse().unwrap()),
+                (&String::from("2024-01-31"), &"01:00:00".parse().unwrap()),
+            ]
+        );
+        // Entries belong to the project's key, not the name inside it.
+        assert!(data
+            .entries
+            .iter()
+            .all(|entry| entry.project == "testshort"));
+        assert_eq!(data.entries.len(), 2);
+        assert_eq!(data.entries[0].start, at("2024-01-30T00:00:00+01:00"));
+        assert_eq!(
+            data.entries[0].duration(),
+            TimeDelta::minutes(7) + TimeDelta::seconds(42)
+        );
+        assert_eq!(data.entries[1].start, at("2024-01-31T00:00:00+01:00"));
+        assert_eq!(data.entries[1].duration(), TimeDelta::hours(1));
+    }
+
+    #[test]
+    fn migrate_v2_to_v3() {
+        berlin();
+        let v2 = serde_json::json!({
+            "version": 2,
+            "projects": {
+                "p": {
+                    "name": "p",
+                    "start_date": "02/07/24 08:55:37",
+                    "hours_per_day": { "02/07/24": "01:00:00" },
+                    "total_time": "01:00:00"
+                }
+            },
+            "entries": [{
+                "project": "p",
+                "start": "2024-02-07T09:00:00+01:00",
+                "end": "2024-02-07T10:00:00+01:00"
+            }]
+        });
+        let data = DataFile::migrate(v2).expect("version 2 migrates");
+        assert_eq!(data.version, DATA_VERSION);
+        let project = &data.projects["p"];
+        assert_eq!(project.start_date, "2024-02-07T08:55:37");
+        assert_eq!(
+            project.hours_per_day.keys().collect::<Vec<_>>(),
+            ["2024-02-07"]
+        );
+        assert_eq!(data.entries.len(), 1);
+        assert_eq!(data.entries[0].start, at("2024-02-07T09:00:00+01:00"));
+    }
+
+    #[test]
+    fn migrate_rejects_newer_versions() {
+        let newer = serde_json::json!({
+            "version": DATA_VERSION + 1,
+            "projects": {},
+            "entries": []
+        });
+        assert!(DataFile::migrate(newer).is_err());
+    }
+}
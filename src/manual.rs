@@ -0,0 +1,337 @@
+use crate::{
+    duration::TrackedTime, invalid, local_midnight, Project, TimeEntry, TimeTracking,
+    DAY_KEY_FORMAT, START_DATE_FORMAT,
+};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeDelta};
+use crossterm::style::Stylize;
+use std::{cmp, io};
+
+/// Note given to entries added by hand rather than tracked.
+const MANUAL_NOTE: &str = "Added manually";
+
+impl TimeEntry {
+    /// Splits the entry in two at `at`, which must fall strictly inside it.
+    fn split_at(self, at: DateTime<Local>) -> (TimeEntry, TimeEntry) {
+        let pauses_between = |start: DateTime<Local>, end: DateTime<Local>| {
+            self.pauses
+                .iter()
+                .filter(|pause| pause.start < end && pause.end.is_none_or(|e| e > start))
+                .cloned()
+                .collect()
+        };
+        let first = TimeEntry {
+            project: self.project.clone(),
+            start: self.start,
+            end: at,
+            pauses: pauses_between(self.start, at),
+            note: self.note.clone(),
+        };
+        let second = TimeEntry {
+            project: self.project.clone(),
+            start: at,
+            end: self.end,
+            pauses: pauses_between(at, self.end),
+            note: self.note.clone(),
+        };
+        (first, second)
+    }
+
+    /// Moves the end earlier until `amount` of active time is removed or nothing is left.
+    /// Returns the active time actually removed.
+    fn shorten(&mut self, amount: TimeDelta) -> TimeDelta {
+        let before = self.duration();
+        let mut left = cmp::max(before - amount, TimeDelta::zero());
+        let mut pauses: Vec<_> = self
+            .pauses
+            .iter()
+            .map(|pause| {
+                (
+                    cmp::max(pause.start, self.start),
+                    cmp::min(pause.end.unwrap_or(self.end), self.end),
+                )
+            })
+            .filter(|(start, end)| start < end)
+            .collect();
+        pauses.sort();
+        // Walk the running stretches between pauses until `left` of them is used up.
+        let mut cursor = self.start;
+        for (start, end) in pauses {
+            if start > cursor {
+                if start - cursor >= left {
+                    break;
+                }
+                left -= start - cursor;
+            }
+            cursor = cmp::max(cursor, end);
+        }
+        self.end = cursor + left;
+        self.pauses.retain(|pause| pause.start < self.end);
+        before - self.duration()
+    }
+}
+
+impl TimeTracking {
+    /// Records `start` to `end` on `project` as if it had been tracked, creating the project
+    /// if needed.
+    pub fn add_time(
+        &mut self,
+        project: &str,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> io::Result<()> {
+        let _lock = self.lock()?;
+        self.add_entry(project, start, end);
+        self.save_to_file()?;
+        println!(
+            "{}",
+            format!(
+                "\u{2714} Added {} to project {:?} on {}.",
+                TrackedTime::from(end - start),
+                project,
+                start.format(&self.config.date_format)
+            )
+            .with(self.config.colors.success())
+        );
+        Ok(())
+    }
+
+    /// Removes `amount` from what was tracked on `project` during `date`, shortening that
+    /// day's entries from the latest one back. Fails rather than removing more than was
+    /// tracked that day.
+    pub fn subtract_time(
+        &mut self,
+        project: &str,
+        date: NaiveDate,
+        amount: TrackedTime,
+    ) -> io::Result<()> {
+        let _lock = self.lock()?;
+        self.remove_time(project, date, amount)?;
+        self.save_to_file()?;
+        println!(
+            "{}",
+            format!(
+                "\u{2714} Subtracted {} from project {:?} on {}.",
+                amount,
+                project,
+                date.format(&self.config.date_format)
+            )
+            .with(self.config.colors.success())
+        );
+        Ok(())
+    }
+
+    /// Makes the time tracked on `project` during `date` add up to `total`, adding a manual
+    /// entry or shortening the day's entries as needed.
+    pub fn set_day_time(
+        &mut self,
+        project: &str,
+        date: NaiveDate,
+        total: TrackedTime,
+    ) -> io::Result<()> {
+        let _lock = self.lock()?;
+        let tracked = self.tracked_on(project, date)?;
+        if total > tracked {
+            let start = local_midnight(date);
+            let end = (total - tracked)
+                .to_delta()
+                .and_then(|extra| start.checked_add_signed(extra))
+                .filter(|end| *end <= local_midnight(date.succ_opt().expect("date out of range")))
+                .ok_or_else(|| invalid(format!("{} doesn't fit in one day", total)))?;
+            self.add_entry(project, start, end);
+        } else if total < tracked {
+            self.remove_time(project, date, tracked - total)?;
+        }
+        self.save_to_file()?;
+        println!(
+            "{}",
+            format!(
+                "\u{2714} Project {:?} now has {} on {} (was {}).",
+                project,
+                total,
+                date.format(&self.config.date_format),
+                tracked
+            )
+            .with(self.config.colors.success())
+        );
+        Ok(())
+    }
+
+    fn add_entry(&mut self, project: &str, start: DateTime<Local>, end: DateTime<Local>) {
+        let start_date = start.format(START_DATE_FORMAT).to_string();
+        let existing = self
+            .projects
+            .entry(project.to_string())
+            .or_insert_with(|| Project::new(project, &start_date));
+        // Time added for a day before the project was created moves its start back.
+        if NaiveDateTime::parse_from_str(&existing.start_date, START_DATE_FORMAT)
+            .is_ok_and(|current| start.naive_local() < current)
+        {
+            existing.start_date = start_date;
+        }
+        self.entries.push(TimeEntry {
+            project: project.to_string(),
+            start,
+            end,
+            pauses: Vec::new(),
+            note: Some(String::from(MANUAL_NOTE)),
+        });
+        self.recompute_totals();
+    }
+
+    fn tracked_on(&self, project: &str, date: NaiveDate) -> io::Result<TrackedTime> {
+        let project = self
+            .projects
+            .get(project)
+            .ok_or_else(|| invalid(format!("no project named {:?}", project)))?;
+        Ok(project
+            .hours_per_day
+            .get(&date.format(DAY_KEY_FORMAT).to_string())
+            .copied()
+            .unwrap_or_default())
+    }
+
+    fn remove_time(
+        &mut self,
+        project: &str,
+        date: NaiveDate,
+        amount: TrackedTime,
+    ) -> io::Result<()> {
+        let tracked = self.tracked_on(project, date)?;
+        if amount > tracked {
+            return Err(invalid(format!(
+                "only {} was tracked on {}",
+                tracked,
+                date.format(&self.config.date_format)
+            )));
+        }
+        let day_start = local_midnight(date);
+        let day_end = local_midnight(date.succ_opt().expect("date out of range"));
+
+        // Cut entries running across midnight so that only the part on `date` gets shortened.
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for entry in self.entries.drain(..) {
+            if entry.project != project {
+                entries.push(entry);
+                continue;
+            }
+            let mut rest = entry;
+            for boundary in [day_start, day_end] {
+                if rest.start < boundary && boundary < rest.end {
+                    let (before, after) = rest.split_at(boundary);
+                    entries.push(before);
+                    rest = after;
+                }
+            }
+            entries.push(rest);
+        }
+
+        let mut on_day: Vec<usize> = (0..entries.len())
+            .filter(|&i| {
+                entries[i].project == project
+                    && entries[i].start >= day_start
+                    && entries[i].end <= day_end
+            })
+            .collect();
+        on_day.sort_by_key(|&i| cmp::Reverse(entries[i].end));
+        let mut left = amount.to_delta().expect("no more than was tracked");
+        let mut emptied = Vec::new();
+        for i in on_day {
+            if left <= TimeDelta::zero() {
+                break;
+            }
+            left -= entries[i].shorten(left);
+            if entries[i].duration() <= TimeDelta::zero() {
+                emptied.push(i);
+            }
+        }
+        self.entries = entries
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| !emptied.contains(i))
+            .map(|(_, entry)| entry)
+            .collect();
+        self.recompute_totals();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pause;
+
+    fn at(time: &str) -> DateTime<Local> {
+        DateTime::parse_from_rfc3339(&format!("2024-02-07T{}:00+01:00", time))
+            .expect("valid time")
+            .with_timezone(&Local)
+    }
+
+    fn entry(start: &str, end: &str, pauses: &[(&str, Option<&str>)]) -> TimeEntry {
+        TimeEntry {
+            project: String::from("p"),
+            start: at(start),
+            end: at(end),
+            pauses: pauses
+                .iter()
+                .map(|(start, end)| Pause {
+                    start: at(start),
+                    end: end.map(at),
+                })
+                .collect(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn shorten_without_pauses() {
+        let mut entry = entry("09:00", "11:00", &[]);
+        assert_eq!(
+            entry.shorten(TimeDelta::minutes(30)),
+            TimeDelta::minutes(30)
+        );
+        assert_eq!(entry.end, at("10:30"));
+    }
+
+    #[test]
+    fn shorten_stops_at_the_start() {
+        let mut entry = entry("09:00", "10:00", &[]);
+        assert_eq!(entry.shorten(TimeDelta::hours(2)), TimeDelta::hours(1));
+        assert_eq!(entry.end, entry.start);
+        assert_eq!(entry.duration(), TimeDelta::zero());
+    }
+
+    #[test]
+    fn shorten_skips_pauses() {
+        // Two hours of work around an hour-long pause.
+        let mut entry = entry("09:00", "12:00", &[("10:00", Some("11:00"))]);
+        assert_eq!(
+            entry.shorten(TimeDelta::minutes(30)),
+            TimeDelta::minutes(30)
+        );
+        assert_eq!(entry.end, at("11:30"));
+        assert_eq!(entry.pauses.len(), 1);
+        assert_eq!(entry.duration(), TimeDelta::minutes(90));
+    }
+
+    #[test]
+    fn shorten_drops_pauses_past_the_new_end() {
+        let mut entry = entry("09:00", "12:00", &[("10:00", Some("11:00"))]);
+        assert_eq!(
+            entry.shorten(TimeDelta::minutes(90)),
+            TimeDelta::minutes(90)
+        );
+        assert_eq!(entry.end, at("09:30"));
+        assert!(entry.pauses.is_empty());
+    }
+
+    #[test]
+    fn shorten_ending_in_an_open_pause() {
+        let mut entry = entry("09:00", "12:00", &[("11:00", None)]);
+        assert_eq!(
+            entry.shorten(TimeDelta::minutes(30)),
+            TimeDelta::minutes(30)
+        );
+        assert_eq!(entry.end, at("10:30"));
+        assert!(entry.pauses.is_empty());
+    }
+}
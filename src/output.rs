@@ -0,0 +1,222 @@
+use crate::{duration::TrackedTime, Project, TimeEntry, TimeTracking, DAY_KEY_FORMAT};
+use chrono::{DateTime, Local, NaiveDate};
+use crossterm::style::Stylize;
+use serde::Serialize;
+use std::{
+    io::{self, Write},
+    process,
+};
+
+/// How the query commands (`status`, `list`, `day`, `report` and `goals`) print their results,
+/// chosen with the global `--output` option.
+///
+/// `Json` prints one object per command, on a single line. The schema is stable, fields are
+/// only ever added to it: durations are whole seconds in fields ending in `_seconds` (or
+/// `seconds`), days are "YYYY-MM-DD" and moments are RFC 3339 with the local offset. Each
+/// command's object is described by the type named in its doc comment below. `Plain` prints
+/// tab-separated lines without colors or box drawing, with durations as "HH:MM:SS".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Output {
+    #[default]
+    Table,
+    Json,
+    Plain,
+}
+
+impl Output {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "json" => Output::Json,
+            "plain" => Output::Plain,
+            _ => Output::Table,
+        }
+    }
+}
+
+/// `status`: the running session. `state` is "running", "paused" or "idle", in which case
+/// every other field is null.
+#[derive(Serialize)]
+pub struct StatusJson<'a> {
+    pub state: &'static str,
+    pub project: Option<&'a str>,
+    pub started: Option<DateTime<Local>>,
+    pub elapsed_seconds: Option<i64>,
+    /// Part of `elapsed_seconds` on the current day, for sessions running since before midnight.
+    pub today_seconds: Option<i64>,
+    pub note: Option<&'a str>,
+}
+
+/// `list`: every project displayed, oldest first.
+#[derive(Serialize)]
+pub struct ProjectListJson<'a> {
+    pub projects: Vec<ProjectJson<'a>>,
+}
+
+/// `list <PROJECT>`: the project with its recorded sessions, oldest first.
+#[derive(Serialize)]
+pub struct ProjectDetailsJson<'a> {
+    pub project: ProjectJson<'a>,
+    pub entries: Vec<EntryJson<'a>>,
+}
+
+/// `day <DAY>`: time per project on the day, with the sessions that touched it.
+#[derive(Serialize)]
+pub struct DayJson<'a> {
+    pub date: String,
+    pub total_seconds: i64,
+    pub projects: Vec<TotalJson>,
+    pub entries: Vec<EntryJson<'a>>,
+}
+
+/// `report`: totals per project, per client and per day from `from` to `to`, inclusive.
+#[derive(Serialize)]
+pub struct ReportJson {
+    pub from: String,
+    pub to: String,
+    pub total_seconds: i64,
+    pub projects: Vec<TotalJson>,
+    pub clients: Vec<TotalJson>,
+    pub days: Vec<DayTotalJson>,
+}
+
+/// `goals`: progress towards each goal, null where none is set. The streaks count days that
+/// met the daily goal and are null without one.
+#[derive(Serialize)]
+pub struct GoalsJson {
+    pub daily: Option<GoalJson>,
+    pub weekly: Option<GoalJson>,
+    pub current_streak_days: Option<u32>,
+    pub longest_streak_days: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct GoalJson {
+    pub goal_seconds: i64,
+    pub tracked_seconds: i64,
+}
+
+#[derive(Serialize)]
+pub struct ProjectJson<'a> {
+    pub name: &'a str,
+    /// When the project was created, in local time as "YYYY-MM-DDTHH:MM:SS".
+    pub start_date: &'a str,
+    pub archived: bool,
+    pub client: Option<&'a str>,
+    pub tags: &'a [String],
+    /// Hourly rate set on the project itself, and its currency if not the default.
+    pub rate: Option<f64>,
+    pub currency: Option<&'a str>,
+    pub budget_seconds: Option<i64>,
+    pub weekly_budget_seconds: Option<i64>,
+    pub total_seconds: i64,
+    /// Time tracked on each day, oldest first.
+    pub days: Vec<DayTotalJson>,
+}
+
+#[derive(Serialize)]
+pub struct EntryJson<'a> {
+    pub project: &'a str,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    /// Time between `start` and `end` that wasn't paused.
+    pub seconds: i64,
+    pub note: Option<&'a str>,
+}
+
+/// Time tracked on a project or for a client.
+#[derive(Serialize)]
+pub struct TotalJson {
+    pub name: String,
+    pub seconds: i64,
+}
+
+#[derive(Serialize)]
+pub struct DayTotalJson {
+    pub date: String,
+    pub seconds: i64,
+}
+
+impl DayTotalJson {
+    pub fn new(date: NaiveDate, time: TrackedTime) -> Self {
+        DayTotalJson {
+            date: date.format(DAY_KEY_FORMAT).to_string(),
+            seconds: time.seconds(),
+        }
+    }
+}
+
+impl<'a> ProjectJson<'a> {
+    pub fn new(project: &'a Project) -> Self {
+        let mut days: Vec<_> = project
+            .hours_per_day
+            .iter()
+            .map(|(day, time)| DayTotalJson {
+                date: day.clone(),
+                seconds: time.seconds(),
+            })
+            .collect();
+        days.sort_by(|a, b| a.date.cmp(&b.date));
+        ProjectJson {
+            name: &project.name,
+            start_date: &project.start_date,
+            archived: project.archived,
+            client: project.client.as_deref(),
+            tags: &project.tags,
+            rate: project.rate,
+            currency: project.currency.as_deref(),
+            budget_seconds: project.budget.total.map(|time| time.seconds()),
+            weekly_budget_seconds: project.budget.weekly.map(|time| time.seconds()),
+            total_seconds: project.total_time.seconds(),
+            days,
+        }
+    }
+}
+
+impl<'a> EntryJson<'a> {
+    pub fn new(entry: &'a TimeEntry, project: &'a str) -> Self {
+        EntryJson {
+            project,
+            start: entry.start,
+            end: entry.end,
+            seconds: entry.duration().num_seconds(),
+            note: entry.note.as_deref(),
+        }
+    }
+}
+
+/// Writes `line` to stdout. A reader that stops early, like `head`, ends the program quietly
+/// instead of making it panic.
+fn emit(line: &str) {
+    if let Err(e) = writeln!(io::stdout(), "{}", line) {
+        if e.kind() == io::ErrorKind::BrokenPipe {
+            process::exit(0);
+        }
+        panic!("failed printing to stdout: {}", e);
+    }
+}
+
+/// Prints `value` as JSON on one line.
+pub fn print_json(value: &impl Serialize) {
+    emit(&serde_json::to_string(value).expect("unable to serialize output"));
+}
+
+/// Prints `fields` as one tab-separated line, with any tabs or line breaks inside them turned
+/// into spaces so every record stays on its line.
+pub fn print_plain<S: AsRef<str>>(fields: &[S]) {
+    let fields: Vec<_> = fields
+        .iter()
+        .map(|field| field.as_ref().replace(['\t', '\n', '\r'], " "))
+        .collect();
+    emit(&fields.join("\t"));
+}
+
+impl TimeTracking {
+    /// Shows a problem with a query: in the warning color as usual, or on stderr without
+    /// decoration when the output is meant for scripts.
+    pub fn warn(&self, message: &str) {
+        match self.output {
+            Output::Table => println!("{}", message.with(self.config.colors.warning())),
+            Output::Json | Output::Plain => eprintln!("{}", message),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::TimeTracking;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+/// An option in the project picker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Choice {
+    Existing { name: String, archived: bool },
+    New(String),
+}
+
+/// What the picker did with a key press.
+pub enum Outcome {
+    Pending,
+    Cancelled,
+    Picked(String),
+}
+
+/// Scores how well `query` fuzzy-matches `candidate`: its characters must appear in order,
+/// and runs of consecutive characters and matches at the start of words score higher.
+/// Returns `None` if it doesn't match at all.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut from = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = from
+            + candidate[from..]
+                .iter()
+                .position(|candidate| *candidate == c)?;
+        score += match previous {
+            Some(previous) if found == previous + 1 => 5,
+            _ => 1,
+        };
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        from = found + 1;
+    }
+    Some(score)
+}
+
+impl TimeTracking {
+    /// Project keys, most recently tracked first, then the ones never tracked by name.
+    pub fn projects_by_recent_use(&self) -> Vec<&String> {
+        let mut keys: Vec<_> = self
+            .projects
+            .keys()
+            .map(|key| {
+                let last_used = self
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.project == *key)
+                    .map(|entry| entry.end)
+                    .max();
+                (key, last_used)
+            })
+            .collect();
+        keys.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        keys.into_iter().map(|(key, _)| key).collect()
+    }
+}
+
+/// Fuzzy-filtered list of projects the `start` dashboard shows on the switch key, so that
+/// switching picks an existing project unless creating a new one is chosen and confirmed.
+pub struct Picker {
+    /// Every project with whether it's archived, most recently used first.
+    projects: Vec<(String, bool)>,
+    running: Option<String>,
+    pub query: String,
+    pub selected: usize,
+    /// Name of the new project waiting for confirmation.
+    pub confirming: Option<String>,
+}
+
+impl Picker {
+    pub fn new(tt: &TimeTracking) -> Self {
+        Picker {
+            projects: tt
+                .projects_by_recent_use()
+                .into_iter()
+                .map(|key| (key.clone(), tt.projects[key].archived))
+                .collect(),
+            running: tt
+                .current_session
+                .as_ref()
+                .map(|session| session.project.clone()),
+            query: String::new(),
+            selected: 0,
+            confirming: None,
+        }
+    }
+
+    /// The projects other than the running one that match the query, best match first,
+    /// followed by the option to create a project named after the query unless one already is.
+    /// Archived projects only show up once something is typed.
+    pub fn choices(&self) -> Vec<Choice> {
+        let query = self.query.trim();
+        let mut matches: Vec<_> = self
+            .projects
+            .iter()
+            .filter(|(name, archived)| {
+                Some(name) != self.running.as_ref() && (!archived || !query.is_empty())
+            })
+            .filter_map(|(name, archived)| Some((fuzzy_score(query, name)?, name, *archived)))
+            .collect();
+        // The sort is stable, so equally good matches stay in order of recent use.
+        matches.sort_by_key(|(score, _, _)| -score);
+        let mut choices: Vec<_> = matches
+            .into_iter()
+            .map(|(_, name, archived)| Choice::Existing {
+                name: name.clone(),
+                archived,
+            })
+            .collect();
+        if !query.is_empty() && !self.projects.iter().any(|(name, _)| name == query) {
+            choices.push(Choice::New(query.to_string()));
+        }
+        choices
+    }
+
+    pub fn handle(&mut self, key: KeyEvent) -> Outcome {
+        if key.kind != KeyEventKind::Press {
+            return Outcome::Pending;
+        }
+        if let Some(name) = self.confirming.take() {
+            return match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => Outcome::Picked(name),
+                _ => Outcome::Pending,
+            };
+        }
+        let choices = self.choices();
+        match key.code {
+            KeyCode::Esc => return Outcome::Cancelled,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Outcome::Cancelled;
+            }
+            KeyCode::Enter => match choices.get(self.selected) {
+                Some(Choice::Existing { name, .. }) => return Outcome::Picked(name.clone()),
+                Some(Choice::New(name)) => self.confirming = Some(name.clone()),
+                None => {}
+            },
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Tab => {
+                self.selected = (self.selected + 1).min(choices.len().saturating_sub(1));
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.query.push(c);
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        Outcome::Pending
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{duration::TrackedTime, invalid, Budget, TimeTracking, START_DATE_FORMAT};
+use chrono::NaiveDateTime;
+use crossterm::style::Stylize;
+use std::io;
+
+impl TimeTracking {
+    /// Fails unless `name` is a project.
+    fn check_exists(&self, name: &str) -> io::Result<()> {
+        if self.projects.contains_key(name) {
+            Ok(())
+        } else {
+            Err(invalid(format!("no project named {:?}", name)))
+        }
+    }
+
+    /// Points the entries and the running session of project `from` at project `to`.
+    fn reassign(&mut self, from: &str, to: &str) {
+        for entry in self
+            .entries
+            .iter_mut()
+            .filter(|entry| entry.project == from)
+        {
+            entry.project = to.to_string();
+        }
+        if let Some(session) = self
+            .current_session
+            .as_mut()
+            .filter(|session| session.project == from)
+        {
+            session.project = to.to_string();
+        }
+    }
+
+    /// Gives project `old` the name `new`, along with its entries and a running session.
+    pub fn rename_project(&mut self, old: &str, new: &str) -> io::Result<()> {
+        let _lock = self.lock()?;
+        self.check_exists(old)?;
+        if self.projects.contains_key(new) {
+            return Err(invalid(format!(
+                "project {:?} already exists, use `merge` to combine them",
+                new
+            )));
+        }
+        let mut project = self.projects.remove(old).expect("checked above");
+        project.name = new.to_string();
+        self.projects.insert(new.to_string(), project);
+        self.reassign(old, new);
+        self.recompute_totals();
+        self.save_to_file()?;
+        println!(
+            "{}",
+            format!("\u{2714} Renamed project {:?} to {:?}.", old, new)
+                .with(self.config.colors.success())
+        );
+        Ok(())
+    }
+
+    /// Removes project `name` and every entry recorded for it.
+    pub fn delete_project(&mut self, name: &str) -> io::Result<()> {
+        let _lock = self.lock()?;
+        self.check_exists(name)?;
+        if self
+            .current_session
+            .as_ref()
+            .is_some_and(|session| session.project == name)
+        {
+            return Err(invalid(format!(
+                "project {:?} is being tracked, stop it first",
+                name
+            )));
+        }
+        let project = self.projects.remove(name).expect("checked above");
+        self.entries.retain(|entry| entry.project != name);
+        self.save_to_file()?;
+        println!(
+            "{}",
+            format!(
+                "\u{2714} Deleted project {:?} and its {} of tracked time.",
+                name, project.total_time
+            )
+            .with(self.config.colors.success())
+        );
+        Ok(())
+    }
+
+    /// Adds `tags` to project `name`, or with `remove` takes them off.
+    pub fn tag_project(&mut self, name: &str, tags: Vec<String>, remove: bool) -> io::Result<()> {
+        let _lock = self.lock()?;
+        self.check_exists(name)?;
+        let project = self.projects.get_mut(name).expect("checked above");
+        if remove {
+            project.tags.retain(|tag| !tags.contains(tag));
+        } else {
+            for tag in tags {
+                if !project.tags.contains(&tag) {
+                    project.tags.push(tag);
+                }
+            }
+            project.tags.sort();
+        }
+        let message = if project.tags.is_empty() {
+            format!("\u{2714} Project {:?} has no tags now.", name)
+        } else {
+            format!(
+                "\u{2714} Project {:?} is now tagged {}.",
+                name,
+                project.tags.join(", ")
+            )
+        };
+        self.save_to_file()?;
+        println!("{}", message.with(self.config.colors.success()));
+        Ok(())
+    }
+
+    /// Sets or clears the client project `name` is for.
+    pub fn set_client(&mut self, name: &str, client: Option<String>) -> io::Result<()> {
+        let _lock = self.lock()?;
+        self.check_exists(name)?;
+        let message = match client {
+            Some(ref client) => format!("\u{2714} Project {:?} is now for {:?}.", name, client),
+            None => format!("\u{2714} Project {:?} has no client now.", name),
+        };
+        self.projects.get_mut(name).expect("checked above").client = client;
+        self.save_to_file()?;
+        println!("{}", message.with(self.config.colors.success()));
+        Ok(())
+    }
+
+    /// Changes the hourly rate and currency billed for project `name`, or with `for_client` the
+    /// defaults for the projects of client `name`. Settings given as `None` are left as they
+    /// are, unless `clear` removes both.
+    pub fn set_rate(
+        &mut self,
+        name: &str,
+        rate: Option<f64>,
+        currency: Option<String>,
+        for_client: bool,
+        clear: bool,
+    ) -> io::Result<()> {
+        if rate.is_some_and(|rate| !rate.is_finite() || rate < 0.0) {
+            return Err(invalid(String::from("rates must be zero or more")));
+        }
+        let _lock = self.lock()?;
+        let (current_rate, current_currency) = if for_client {
+            let client = self.clients.entry(name.to_string()).or_default();
+            (&mut client.rate, &mut client.currency)
+        } else {
+            self.check_exists(name)?;
+            let project = self.projects.get_mut(name).expect("checked above");
+            (&mut project.rate, &mut project.currency)
+        };
+        if clear {
+            *current_rate = None;
+            *current_currency = None;
+        }
+        if rate.is_some() {
+            *current_rate = rate;
+        }
+        if currency.is_some() {
+            *current_currency = currency;
+        }
+        let message = match (*current_rate, current_currency.as_ref()) {
+            (Some(rate), Some(currency)) => format!("{:.2} {}/h", rate, currency),
+            (Some(rate), None) => format!("{:.2}/h", rate),
+            (None, Some(currency)) => format!("no rate in {}", currency),
+            (None, None) => String::from("no rate"),
+        };
+        if for_client {
+            self.clients
+                .retain(|_, client| client.rate.is_some() || client.currency.is_some());
+        }
+        self.save_to_file()?;
+        let kind = if for_client { "Client" } else { "Project" };
+        println!(
+            "{}",
+            format!("\u{2714} {} {:?} now has {}.", kind, name, message)
+                .with(self.config.colors.success())
+        );
+        Ok(())
+    }
+
+    /// Changes the budgets of project `name`. Budgets given as `None` are left as they are,
+    /// unless `clear` removes both.
+    pub fn set_budget(
+        &mut self,
+        name: &str,
+        total: Option<TrackedTime>,
+        weekly: Option<TrackedTime>,
+        clear: bool,
+    ) -> io::Result<()> {
+        let _lock = self.lock()?;
+        self.check_exists(name)?;
+        let project = self.projects.get_mut(name).expect("checked above");
+        if clear {
+            project.budget = Budget::default();
+        }
+        project.budget.total = total.or(project.budget.total);
+        project.budget.weekly = weekly.or(project.budget.weekly);
+        let message = match (project.budget.total, project.budget.weekly) {
+            (None, None) => format!("\u{2714} Project {:?} has no budget now.", name),
+            (total, weekly) => {
+                let budgets: Vec<_> = [(total, "overall"), (weekly, "a week")]
+                    .into_iter()
+                    .filter_map(|(time, period)| Some(format!("{} {}", time?, period)))
+                    .collect();
+                format!(
+                    "\u{2714} Project {:?} is now budgeted {}.",
+                    name,
+                    budgets.join(" and ")
+                )
+            }
+        };
+        self.save_to_file()?;
+        println!("{}", message.with(self.config.colors.success()));
+        Ok(())
+    }
+
+    /// Hides project `name` from `list`, or shows it again.
+    pub fn set_archived(&mut self, name: &str, archived: bool) -> io::Result<()> {
+        let _lock = self.lock()?;
+        self.check_exists(name)?;
+        let project = self.projects.get_mut(name).expect("checked above");
+        if project.archived == archived {
+            let state = if archived { "already" } else { "not" };
+            println!(
+                "{}",
+                format!("\u{1f6c8} Project {:?} is {} archived.", name, state)
+                    .with(self.config.colors.info())
+            );
+            return Ok(());
+        }
+        project.archived = archived;
+        self.save_to_file()?;
+        let verb = if archived { "Archived" } else { "Unarchived" };
+        println!(
+            "{}",
+            format!("\u{2714} {} project {:?}.", verb, name).with(self.config.colors.success())
+        );
+        Ok(())
+    }
+
+    /// Moves everything recorded for project `source` into project `target`, which keeps the
+    /// earlier of the two start dates, and removes `source`.
+    pub fn merge_projects(&mut self, source: &str, target: &str) -> io::Result<()> {
+        let _lock = self.lock()?;
+        self.check_exists(source)?;
+        self.check_exists(target)?;
+        if source == target {
+            return Err(invalid(String::from("can't merge a project into itself")));
+        }
+        let source_project = self.projects.remove(source).expect("checked above");
+        let target_project = self.projects.get_mut(target).expect("checked above");
+        let started =
+            |start_date: &str| NaiveDateTime::parse_from_str(start_date, START_DATE_FORMAT).ok();
+        if started(&source_project.start_date).is_some_and(|source_start| {
+            started(&target_project.start_date)
+                .is_none_or(|target_start| source_start < target_start)
+        }) {
+            target_project.start_date = source_project.start_date;
+        }
+        self.reassign(source, target);
+        self.recompute_totals();
+        self.save_to_file()?;
+        println!(
+            "{}",
+            format!(
+                "\u{2714} Merged {} from project {:?} into {:?}, which now has {}.",
+                source_project.total_time, source, target, self.projects[target].total_time
+            )
+            .with(self.config.colors.success())
+        );
+        Ok(())
+    }
+}
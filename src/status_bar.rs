@@ -0,0 +1,183 @@
+use crate::{duration::TrackedTime, output::print_json, Session, TimeTracking};
+use serde::Serialize;
+
+/// Template `status --waybar` fills in when no `--format` is given.
+pub const DEFAULT_TEMPLATE: &str = "{project} {elapsed}";
+
+/// Placeholders a status template can contain.
+const PLACEHOLDERS: [&str; 6] = ["project", "state", "elapsed", "today", "started", "note"];
+
+/// What waybar's custom modules read from each line: `class` and `alt` are "running",
+/// "paused" or "idle", and `percentage` is the progress towards the daily goal, if set.
+#[derive(Serialize)]
+struct Waybar {
+    text: String,
+    tooltip: String,
+    class: &'static str,
+    alt: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    percentage: Option<i64>,
+}
+
+/// Fills the `{name}` placeholders in `template` with `value(name)`; "{{" and "}}" stand for
+/// literal braces. Fails on unknown placeholders and unbalanced braces.
+fn fill(template: &str, value: impl Fn(&str) -> String) -> Result<String, String> {
+    let mut filled = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                filled.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                filled.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("unclosed \"{{\" in {:?}", template)),
+                    }
+                }
+                if !PLACEHOLDERS.contains(&name.as_str()) {
+                    return Err(format!(
+                        "unknown placeholder {{{}}}, expected one of {}",
+                        name,
+                        PLACEHOLDERS.map(|name| format!("{{{}}}", name)).join(", ")
+                    ));
+                }
+                filled.push_str(&value(&name));
+            }
+            '}' => return Err(format!("unmatched \"}}\" in {:?}, use \"}}}}\"", template)),
+            c => filled.push(c),
+        }
+    }
+    Ok(filled)
+}
+
+/// Checks a `--format` template, for use as a clap value parser.
+pub fn parse_template(template: &str) -> Result<String, String> {
+    fill(template, |_| String::new()).map(|_| template.to_string())
+}
+
+impl TimeTracking {
+    /// `template` filled in with the details of `session`.
+    fn fill_status(&self, template: &str, session: &Session) -> String {
+        fill(template, |name| match name {
+            "project" => session.project.clone(),
+            "state" => String::from(if session.is_paused() {
+                "paused"
+            } else {
+                "running"
+            }),
+            "elapsed" => TrackedTime::from(session.elapsed()).to_string(),
+            "today" => TrackedTime::from(session.elapsed_today()).to_string(),
+            "started" => session.start.format("%H:%M").to_string(),
+            "note" => session.note.clone().unwrap_or_default(),
+            _ => String::new(),
+        })
+        .expect("template checked by parse_template")
+    }
+
+    /// Prints the running session as `template` on one line for status bars such as tmux,
+    /// i3blocks or polybar, or `idle` if nothing is being tracked.
+    pub fn status_line(&self, template: &str, idle: &str) {
+        match self.current_session {
+            Some(ref session) => println!("{}", self.fill_status(template, session)),
+            None => println!("{}", idle),
+        }
+    }
+
+    /// Prints the running session as the JSON a waybar custom module with `return-type` set
+    /// to "json" expects, with `template` as its text.
+    pub fn status_waybar(&self, template: &str, idle: &str) {
+        let percentage = self.config.goals.daily().map(|goal| {
+            let today = self.tracked_today();
+            if goal.seconds() > 0 {
+                today.seconds() * 100 / goal.seconds()
+            } else {
+                100
+            }
+        });
+        let waybar = match self.current_session {
+            Some(ref session) => {
+                let state = if session.is_paused() {
+                    "paused"
+                } else {
+                    "running"
+                };
+                let mut tooltip = format!(
+                    "{}: {}, {} since {}",
+                    session.project,
+                    state,
+                    TrackedTime::from(session.elapsed()),
+                    session.start.format("%H:%M")
+                );
+                if let Some(ref note) = session.note {
+                    tooltip.push_str(&format!("\n{}", note));
+                }
+                Waybar {
+                    text: self.fill_status(template, session),
+                    tooltip,
+                    class: state,
+                    alt: state,
+                    percentage,
+                }
+            }
+            None => Waybar {
+                text: idle.to_string(),
+                tooltip: String::from("No project is being tracked"),
+                class: "idle",
+                alt: "idle",
+                percentage,
+            },
+        };
+        print_json(&waybar);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill_upper(template: &str) -> Result<String, String> {
+        fill(template, |name| name.to_uppercase())
+    }
+
+    #[test]
+    fn fills_placeholders() {
+        assert_eq!(
+            fill_upper("{project} {elapsed}").as_deref(),
+            Ok("PROJECT ELAPSED")
+        );
+        assert_eq!(
+            fill_upper("[{state}] {today} since {started}: {note}").as_deref(),
+            Ok("[STATE] TODAY since STARTED: NOTE")
+        );
+        assert_eq!(fill_upper("").as_deref(), Ok(""));
+        assert_eq!(
+            fill_upper("\u{23f1} plain").as_deref(),
+            Ok("\u{23f1} plain")
+        );
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(fill_upper("{{{project}}}").as_deref(), Ok("{PROJECT}"));
+        assert_eq!(fill_upper("{{project}}").as_deref(), Ok("{project}"));
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        assert!(fill_upper("{nope}").is_err());
+        assert!(fill_upper("{project").is_err());
+        assert!(fill_upper("project}").is_err());
+        assert!(fill_upper("{}").is_err());
+        assert_eq!(parse_template("{project}"), Ok(String::from("{project}")));
+        assert!(parse_template("{elapsed").is_err());
+    }
+}
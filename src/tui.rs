@@ -0,0 +1,480 @@
+use crate::{
+    duration::TrackedTime,
+    picker::{Choice, Outcome, Picker},
+    TimeTracking, DAY_KEY_FORMAT,
+};
+use chrono::Local;
+use crossterm::{
+    cursor,
+    event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor, Stylize},
+    terminal,
+};
+use std::{
+    cmp,
+    io::{self, stdout, Write},
+};
+
+/// How many of the latest messages the dashboard keeps on screen.
+const MESSAGE_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Plain,
+    Heading,
+    /// Reverse video across the whole width, for the title bar and the footer.
+    Bar,
+}
+
+/// One row of the dashboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Line {
+    text: String,
+    color: Option<Color>,
+    style: Style,
+}
+
+impl Line {
+    fn plain(text: String) -> Self {
+        Line {
+            text,
+            color: None,
+            style: Style::Plain,
+        }
+    }
+
+    fn heading(text: &str) -> Self {
+        Line {
+            text: text.to_string(),
+            color: None,
+            style: Style::Heading,
+        }
+    }
+
+    fn bar(text: String) -> Self {
+        Line {
+            text,
+            color: None,
+            style: Style::Bar,
+        }
+    }
+
+    fn blank() -> Self {
+        Line::plain(String::new())
+    }
+}
+
+/// What a dialog on the dashboard asks for, so that the `start` loop knows what to do with
+/// the answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialog {
+    /// The project to switch to.
+    Switch,
+    /// The project to give the time away to.
+    GiveIdleTime,
+    /// The note of the running session.
+    Note,
+}
+
+/// How a dialog takes its answer.
+enum Input {
+    Picker(Picker),
+    Line { prompt: String, text: String },
+}
+
+/// Cuts `text` to `width` characters, or pads it with spaces to exactly that width.
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted: String = text.chars().take(width).collect();
+    let len = fitted.chars().count();
+    fitted.push_str(&" ".repeat(width - len));
+    fitted
+}
+
+/// Full-screen view of the running session that the `start` loop draws on the alternate
+/// screen: the project and its timer, today's totals per project, the latest sessions, the
+/// latest messages and the keys. The terminal is restored when it is dropped.
+pub struct Dashboard {
+    /// Rows currently on screen, so that only the ones that change are drawn again.
+    drawn: Vec<Line>,
+    /// Size of the terminal they were drawn for, or zero when everything needs drawing.
+    size: (u16, u16),
+    messages: Vec<(String, Color)>,
+    /// Question waiting for an answer, shown above the keys until it is cleared.
+    pub question: Option<String>,
+    /// Dialog taking the key presses instead of the `start` loop, while one is open.
+    dialog: Option<(Dialog, Input)>,
+}
+
+impl Dashboard {
+    /// Switches to the alternate screen in raw mode.
+    pub fn open() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Dashboard {
+            drawn: Vec::new(),
+            size: (0, 0),
+            messages: Vec::new(),
+            question: None,
+            dialog: None,
+        })
+    }
+
+    /// Restores the terminal from a panic hook, where the dashboard itself can't be dropped.
+    pub fn restore_terminal() {
+        let _ = execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+
+    /// Makes the next `draw` repaint everything, e.g. after the terminal was resized.
+    pub fn invalidate(&mut self) {
+        self.size = (0, 0);
+    }
+
+    /// Brings the screen up to date with `tt`, drawing only the rows that changed.
+    pub fn draw(&mut self, tt: &TimeTracking) -> io::Result<()> {
+        self.messages.extend(tt.messages.borrow_mut().drain(..));
+        let excess = self.messages.len().saturating_sub(MESSAGE_LINES);
+        self.messages.drain(..excess);
+        self.render(|dashboard, height| match dashboard.dialog {
+            Some((dialog, Input::Picker(ref picker))) => {
+                dashboard.picker_frame(tt, dialog, picker, height)
+            }
+            _ => dashboard.frame(tt, height),
+        })
+    }
+
+    /// Draws the rows `frame` builds for the terminal's height that differ from the ones on
+    /// screen.
+    fn render(&mut self, frame: impl FnOnce(&Self, usize) -> Vec<Line>) -> io::Result<()> {
+        let size = terminal::size()?;
+        let mut out = stdout();
+        if size != self.size {
+            self.size = size;
+            self.drawn.clear();
+            queue!(out, terminal::Clear(terminal::ClearType::All))?;
+        }
+        let (width, height) = (usize::from(size.0), usize::from(size.1));
+        let frame = frame(self, height);
+        for (row, line) in frame.iter().enumerate() {
+            if self.drawn.get(row) == Some(line) {
+                continue;
+            }
+            queue!(out, cursor::MoveTo(0, row as u16))?;
+            match line.style {
+                Style::Plain => {}
+                Style::Heading => queue!(out, SetAttribute(Attribute::Bold))?,
+                Style::Bar => queue!(out, SetAttribute(Attribute::Reverse))?,
+            }
+            if let Some(color) = line.color {
+                queue!(out, SetForegroundColor(color))?;
+            }
+            queue!(
+                out,
+                Print(fit(&line.text, width)),
+                SetAttribute(Attribute::Reset),
+                ResetColor
+            )?;
+        }
+        out.flush()?;
+        self.drawn = frame;
+        Ok(())
+    }
+
+    /// Opens a fuzzy-filtered list of the existing projects to pick from for `dialog`, with the
+    /// option to confirm creating a new one.
+    pub fn pick_project(&mut self, tt: &TimeTracking, dialog: Dialog) {
+        self.dialog = Some((dialog, Input::Picker(Picker::new(tt))));
+    }
+
+    /// Opens a line of input for `dialog`, typed on the footer row after `prompt`.
+    pub fn prompt(&mut self, dialog: Dialog, prompt: String) {
+        self.dialog = Some((
+            dialog,
+            Input::Line {
+                prompt,
+                text: String::new(),
+            },
+        ));
+    }
+
+    pub fn dialog_open(&self) -> bool {
+        self.dialog.is_some()
+    }
+
+    /// Passes a key press to the open dialog. Returns the dialog once it closes, with the
+    /// answer or `None` if it was cancelled.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<(Dialog, Option<String>)> {
+        let (dialog, ref mut input) = *self.dialog.as_mut()?;
+        let answer = match input {
+            Input::Picker(picker) => match picker.handle(key) {
+                Outcome::Pending => return None,
+                Outcome::Cancelled => None,
+                Outcome::Picked(name) => Some(name),
+            },
+            Input::Line { text, .. } => {
+                if key.kind != KeyEventKind::Press {
+                    return None;
+                }
+                match key.code {
+                    KeyCode::Enter => Some(text.trim().to_string()),
+                    KeyCode::Esc => None,
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => None,
+                    KeyCode::Backspace => {
+                        text.pop();
+                        return None;
+                    }
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        text.push(c);
+                        return None;
+                    }
+                    _ => return None,
+                }
+            }
+        };
+        self.dialog = None;
+        Some((dialog, answer))
+    }
+
+    /// The bar at the top with the running project, whether it's paused and its timer.
+    fn title(tt: &TimeTracking) -> Line {
+        match tt.current_session {
+            Some(ref session) => {
+                let state = if session.is_paused() {
+                    "Paused"
+                } else {
+                    "Running"
+                };
+                Line::bar(format!(
+                    " {} \u{2016} {} \u{2016} {}",
+                    session.project,
+                    state,
+                    session.format_elapsed()
+                ))
+            }
+            None => Line::bar(String::from(" No project is being tracked")),
+        }
+    }
+
+    /// Rows of the screen while `picker` is open for `dialog`.
+    fn picker_frame(
+        &self,
+        tt: &TimeTracking,
+        dialog: Dialog,
+        picker: &Picker,
+        height: usize,
+    ) -> Vec<Line> {
+        let heading = match dialog {
+            Dialog::GiveIdleTime => "Give the time away to",
+            _ => "Switch to",
+        };
+        let mut frame = vec![
+            Dashboard::title(tt),
+            Line::blank(),
+            Line::heading(&format!("{}: {}_", heading, picker.query)),
+            Line::blank(),
+        ];
+        let choices = picker.choices();
+        if choices.is_empty() {
+            frame.push(Line::plain(String::from("  No projects yet, type a name.")));
+        }
+        // Scroll so that the selected choice stays visible.
+        let room = cmp::max(height.saturating_sub(frame.len() + 1), 1);
+        let skip = (picker.selected + 1).saturating_sub(room);
+        for (i, choice) in choices.iter().enumerate().skip(skip).take(room) {
+            let mut line = match choice {
+                Choice::Existing { name, archived } => Line::plain(format!(
+                    "  {}{}",
+                    name,
+                    if *archived { " (archived)" } else { "" }
+                )),
+                Choice::New(name) => Line {
+                    text: format!("  + Create new project {:?}", name),
+                    color: Some(tt.config.colors.success()),
+                    style: Style::Plain,
+                },
+            };
+            if i == picker.selected {
+                line.style = Style::Bar;
+            }
+            frame.push(line);
+        }
+        frame.truncate(height.saturating_sub(1));
+        frame.resize(height.saturating_sub(1), Line::blank());
+        frame.push(match picker.confirming {
+            Some(ref name) => Line {
+                text: format!(" Create project {:?}? [y/N]", name),
+                color: Some(tt.config.colors.warning()),
+                style: Style::Bar,
+            },
+            None => Line::bar(String::from(
+                " [Enter] pick  [\u{2191}/\u{2193}] move  [Esc] cancel",
+            )),
+        });
+        frame.truncate(height);
+        frame
+    }
+
+    /// Every row of a screen `height` lines tall, before they are cut to its width.
+    fn frame(&self, tt: &TimeTracking, height: usize) -> Vec<Line> {
+        let now = Local::now();
+        let mut top = vec![Dashboard::title(tt)];
+        if let Some(ref session) = tt.current_session {
+            if let Some(ref note) = session.note {
+                top.push(Line::plain(format!("  Note: {}", note)));
+            }
+            if let Some(progress) = tt.daily_progress() {
+                top.push(Line::plain(format!("  {}", progress)));
+            }
+            if let Some(project) = tt.projects.get(&session.project) {
+                for usage in project.budget_usage(&session.to_entry(now).time_per_day()) {
+                    let mut line = Line::plain(format!("  {}: {}", usage.label, usage.describe()));
+                    if usage.used > usage.budget {
+                        line.color = Some(tt.config.colors.warning());
+                    }
+                    top.push(line);
+                }
+            }
+        }
+
+        let today = now.date_naive();
+        let today_key = today.format(DAY_KEY_FORMAT).to_string();
+        let mut totals: Vec<(&str, TrackedTime)> = tt
+            .projects
+            .iter()
+            .map(|(key, project)| {
+                let mut time = project
+                    .hours_per_day
+                    .get(&today_key)
+                    .copied()
+                    .unwrap_or_default();
+                if let Some(session) = tt
+                    .current_session
+                    .as_ref()
+                    .filter(|session| session.project == *key)
+                {
+                    time += session.elapsed_today().into();
+                }
+                (key.as_str(), time)
+            })
+            .filter(|(_, time)| *time > TrackedTime::ZERO)
+            .collect();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let namelen = totals
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .chain(["Total".len()])
+            .max()
+            .unwrap_or(0);
+        top.push(Line::blank());
+        top.push(Line::heading(&format!(
+            "Today, {}",
+            today.format(&tt.config.date_format)
+        )));
+        if totals.is_empty() {
+            top.push(Line::plain(String::from("  Nothing tracked yet.")));
+        } else {
+            for (name, time) in totals.iter() {
+                top.push(Line::plain(format!(
+                    "  {:<width$}  {}",
+                    name,
+                    time,
+                    width = namelen
+                )));
+            }
+            top.push(Line::plain(format!(
+                "  {:<width$}  {}",
+                "Total",
+                totals.iter().map(|(_, time)| *time).sum::<TrackedTime>(),
+                width = namelen
+            )));
+        }
+
+        let mut footer: Vec<Line> = self
+            .messages
+            .iter()
+            .map(|(message, color)| Line {
+                text: message.clone(),
+                color: Some(*color),
+                style: Style::Plain,
+            })
+            .collect();
+        if let Some(ref question) = self.question {
+            footer.push(Line {
+                text: question.clone(),
+                color: Some(tt.config.colors.warning()),
+                style: Style::Heading,
+            });
+        }
+        let keys: Vec<_> = tt
+            .config
+            .keys
+            .help()
+            .iter()
+            .map(|(key, action)| format!("[{}] {}", key, action))
+            .collect();
+        footer.push(match self.dialog {
+            Some((
+                _,
+                Input::Line {
+                    ref prompt,
+                    ref text,
+                },
+            )) => Line::plain(format!("{}{}_", prompt, text)),
+            _ => Line::bar(format!(" {}", keys.join("  "))),
+        });
+
+        // Recent sessions get whatever room the other sections leave.
+        let room = height.saturating_sub(top.len() + footer.len() + 3);
+        if room > 0 && !tt.entries.is_empty() {
+            let mut entries: Vec<_> = tt.entries.iter().collect();
+            entries.sort_by_key(|entry| cmp::Reverse(entry.end));
+            entries.truncate(room);
+            let namelen = entries
+                .iter()
+                .map(|entry| entry.project.chars().count())
+                .max()
+                .unwrap_or(0);
+            top.push(Line::blank());
+            top.push(Line::heading("Recent sessions"));
+            for entry in entries {
+                let mut text = format!(
+                    "  {} {}-{}  {:<width$}  {}",
+                    entry.start.format(&tt.config.date_format),
+                    entry.start.format("%H:%M"),
+                    entry.end.format("%H:%M"),
+                    entry.project,
+                    TrackedTime::from(entry.duration()),
+                    width = namelen
+                );
+                if let Some(ref note) = entry.note {
+                    text.push_str(&format!("  {}", note));
+                }
+                top.push(Line::plain(text));
+            }
+        }
+
+        let mut frame = top;
+        frame.truncate(height.saturating_sub(footer.len()));
+        let footer_start = cmp::max(height, frame.len() + footer.len()) - footer.len();
+        frame.resize(footer_start, Line::blank());
+        frame.extend(footer);
+        frame.truncate(height);
+        frame
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        Dashboard::restore_terminal();
+    }
+}
+
+impl TimeTracking {
+    /// Prints the messages queued while `interactive` once the dashboard is gone.
+    pub fn print_messages(&self) {
+        for (message, color) in self.messages.borrow_mut().drain(..) {
+            println!("{}", message.with(color));
+        }
+    }
+}